@@ -2,32 +2,117 @@
 
 # subcommands
 complete -f -c widots -n "__fish_use_subcommand" -a "link" -d "Link dotfiles"
+complete -f -c widots -n "__fish_use_subcommand" -a "unlink" -d "Remove links to dotfiles"
+complete -f -c widots -n "__fish_use_subcommand" -a "prune" -d "Remove links the dotfiles directory no longer produces"
+complete -f -c widots -n "__fish_use_subcommand" -a "watch" -d "Keep dotfiles linked while the dotfiles directory changes"
+complete -f -c widots -n "__fish_use_subcommand" -a "add" -d "Move files into the dotfiles directory and link them back"
+complete -f -c widots -n "__fish_use_subcommand" -a "encrypt" -d "Encrypt secret files into the dotfiles directory"
+complete -f -c widots -n "__fish_use_subcommand" -a "status" -d "Report dotfiles whose links have drifted"
+complete -f -c widots -n "__fish_use_subcommand" -a "diff" -d "Show how deployed copies and templates differ from their source"
 complete -f -c widots -n "__fish_use_subcommand" -a "materialize" -d "Materialize dotfiles"
 complete -f -c widots -n "__fish_use_subcommand" -a "load" -d "Apply configuration from TOML file"
+complete -f -c widots -n "__fish_use_subcommand" -a "sync" -d "Pull, load, commit and push the dotfiles repository"
+complete -f -c widots -n "__fish_use_subcommand" -a "restore" -d "Restore files backed up when linking"
 complete -f -c widots -n "__fish_use_subcommand" -a "brew" -d "Manage Homebrew"
 complete -f -c widots -n "__fish_use_subcommand" -a "deploy" -d "Deploy widots to the local machine"
 complete -f -c widots -n "__fish_use_subcommand" -a "fish" -d "Manage Fish shell"
 complete -f -c widots -n "__fish_use_subcommand" -a "vscode" -d "Manage Visual Studio Code extensions by using code command"
 complete -f -c widots -n "__fish_use_subcommand" -s v -l verbose -d "Output verbose information"
 complete -f -c widots -n "__fish_use_subcommand" -s vv -d "Output verbose information more loudly"
+complete -f -c widots -n "__fish_seen_subcommand_from link unlink prune load" -l dry-run -d "Print the planned changes without touching the filesystem"
 
 # link
 complete -c widots -n "__fish_seen_subcommand_from link" -s t -l test -d "Test the dotfiles directory for symlinks and files"
 complete -c widots -n "__fish_seen_subcommand_from link" -s f -l force -d "Force create symlinks, overwriting existing files"
+complete -c widots -n "__fish_seen_subcommand_from link" -l gitignore -d "Also skip files excluded by .gitignore"
+complete -c widots -n "__fish_seen_subcommand_from link" -l fold -d "Link whole directories that do not exist yet"
+complete -c widots -n "__fish_seen_subcommand_from link" -l relative -d "Create links with relative paths"
+complete -x -c widots -n "__fish_seen_subcommand_from link" -l on-conflict -a "skip overwrite backup adopt ask" -d "What to do when a target file already exists"
+complete -x -c widots -n "__fish_seen_subcommand_from link" -l method -a "symlink copy hardlink" -d "How to place each file in the target"
+complete -x -c widots -n "__fish_seen_subcommand_from link" -l deny-chars -d "Skip files whose names contain any of these characters"
+complete -c widots -n "__fish_seen_subcommand_from link" -s c -l config -r -d "The TOML file whose [vars] templates are rendered with"
 complete -c widots -n "__fish_seen_subcommand_from link" -a "(__fish_complete_path)"
 
+# unlink
+complete -c widots -n "__fish_seen_subcommand_from unlink" -s t -l test -d "Unlink from the test directory"
+complete -c widots -n "__fish_seen_subcommand_from unlink" -a "(__fish_complete_path)"
+
+# prune
+complete -c widots -n "__fish_seen_subcommand_from prune" -s t -l test -d "Prune the test directory"
+complete -c widots -n "__fish_seen_subcommand_from prune" -l gitignore -d "Also skip files excluded by .gitignore"
+complete -c widots -n "__fish_seen_subcommand_from prune" -l fold -d "Link whole directories that do not exist yet"
+complete -c widots -n "__fish_seen_subcommand_from prune" -s c -l config -r -d "The TOML file whose [vars] templates are rendered with"
+complete -c widots -n "__fish_seen_subcommand_from prune" -a "(__fish_complete_path)"
+
+# watch
+complete -c widots -n "__fish_seen_subcommand_from watch" -s t -l test -d "Link to the test directory"
+complete -c widots -n "__fish_seen_subcommand_from watch" -l gitignore -d "Also skip files excluded by .gitignore"
+complete -c widots -n "__fish_seen_subcommand_from watch" -l fold -d "Link whole directories that do not exist yet"
+complete -c widots -n "__fish_seen_subcommand_from watch" -l relative -d "Create links with relative paths"
+complete -x -c widots -n "__fish_seen_subcommand_from watch" -l on-conflict -a "skip overwrite backup adopt ask" -d "What to do when a target file already exists"
+complete -x -c widots -n "__fish_seen_subcommand_from watch" -l method -a "symlink copy hardlink" -d "How to place each file in the target"
+complete -x -c widots -n "__fish_seen_subcommand_from watch" -l deny-chars -d "Skip files whose names contain any of these characters"
+complete -c widots -n "__fish_seen_subcommand_from watch" -s c -l config -r -d "The TOML file whose [vars] templates are rendered with"
+complete -c widots -n "__fish_seen_subcommand_from watch" -a "(__fish_complete_path)"
+
+# add
+complete -c widots -n "__fish_seen_subcommand_from add" -s r -l repo -r -a "(__fish_complete_directories)" -d "The dotfiles directory to add the files to"
+complete -c widots -n "__fish_seen_subcommand_from add" -s t -l test -d "Add from the test directory"
+complete -c widots -n "__fish_seen_subcommand_from add" -s c -l config -r -d "The TOML file whose [[link]] entry decides which names are refused"
+complete -c widots -n "__fish_seen_subcommand_from add" -s p -l profile -r -d "The profile to take the [[link]] entry from"
+complete -c widots -n "__fish_seen_subcommand_from add" -a "(__fish_complete_path)"
+
+# encrypt
+complete -c widots -n "__fish_seen_subcommand_from encrypt" -s r -l repo -r -a "(__fish_complete_directories)" -d "The dotfiles directory to add the encrypted files to"
+complete -c widots -n "__fish_seen_subcommand_from encrypt" -s t -l test -d "Encrypt from the test directory"
+complete -c widots -n "__fish_seen_subcommand_from encrypt" -s c -l config -r -d "The TOML file whose [[link]] entry decides which names are refused"
+complete -c widots -n "__fish_seen_subcommand_from encrypt" -s p -l profile -r -d "The profile to take the [[link]] entry from"
+complete -c widots -n "__fish_seen_subcommand_from encrypt" -a "(__fish_complete_path)"
+
+# status
+complete -c widots -n "__fish_seen_subcommand_from status" -s t -l test -d "Check the test directory"
+complete -c widots -n "__fish_seen_subcommand_from status" -s a -l all -d "Also list dotfiles that are linked as expected"
+complete -c widots -n "__fish_seen_subcommand_from status" -l gitignore -d "Also skip files excluded by .gitignore"
+complete -c widots -n "__fish_seen_subcommand_from status" -l fold -d "Link whole directories that do not exist yet"
+complete -c widots -n "__fish_seen_subcommand_from status" -s c -l config -r -d "Also check the permissions declared in this TOML file"
+complete -c widots -n "__fish_seen_subcommand_from status" -s p -l profile -r -d "The profile whose permissions to check"
+complete -c widots -n "__fish_seen_subcommand_from status" -a "(__fish_complete_path)"
+
+# diff
+complete -c widots -n "__fish_seen_subcommand_from diff" -s r -l repo -r -a "(__fish_complete_directories)" -d "The dotfiles directory the files are deployed from"
+complete -c widots -n "__fish_seen_subcommand_from diff" -s t -l test -d "Diff against the test directory"
+complete -c widots -n "__fish_seen_subcommand_from diff" -l stat -d "Only summarize how many lines changed in each file"
+complete -c widots -n "__fish_seen_subcommand_from diff" -l gitignore -d "Also skip files excluded by .gitignore"
+complete -c widots -n "__fish_seen_subcommand_from diff" -s c -l config -r -d "The TOML file whose [[link]] entry and [vars] to diff with"
+complete -c widots -n "__fish_seen_subcommand_from diff" -s p -l profile -r -d "The profile to diff with"
+complete -c widots -n "__fish_seen_subcommand_from diff" -a "(__fish_complete_path)"
+
 # materialize
+complete -c widots -n "__fish_seen_subcommand_from materialize" -s s -l source -r -a "(__fish_complete_directories)" -d "Only materialize links into this dotfiles directory"
 complete -c widots -n "__fish_seen_subcommand_from materialize" -a "(__fish_complete_path)"
 
 # load
 complete -c widots -n "__fish_seen_subcommand_from load" -s t -l test -d "Test the dotfiles directory for symlinks and files"
+complete -c widots -n "__fish_seen_subcommand_from load" -s p -l profile -r -d "The profile whose links and provisions to apply"
 complete -c widots -n "__fish_seen_subcommand_from load" -s f -l force -d "Force create symlinks, overwriting existing files"
 complete -c widots -n "__fish_seen_subcommand_from load" -a "(__fish_complete_path)"
 
+# sync
+complete -c widots -n "__fish_seen_subcommand_from sync" -s r -l repo -r -a "(__fish_complete_directories)" -d "The dotfiles git repository"
+complete -c widots -n "__fish_seen_subcommand_from sync" -s t -l test -d "Link to the test directory"
+complete -c widots -n "__fish_seen_subcommand_from sync" -s p -l profile -r -d "The profile to load"
+complete -c widots -n "__fish_seen_subcommand_from sync" -l commit -d "Commit local changes with a generated message"
+complete -c widots -n "__fish_seen_subcommand_from sync" -l no-push -d "Do not push to the remote after syncing"
+complete -c widots -n "__fish_seen_subcommand_from sync" -a "(__fish_complete_path)"
+
+# restore
+complete -f -c widots -n "__fish_seen_subcommand_from restore" -s l -l list -d "List the recorded backup runs"
+
 # brew
 complete -f -c widots -n "__fish_seen_subcommand_from brew" -a "install" -d "Install Homebrew itself"
 complete -f -c widots -n "__fish_seen_subcommand_from brew" -a "import" -d "Import Homebrew packages"
 complete -f -c widots -n "__fish_seen_subcommand_from brew" -a "export" -d "Export Homebrew packages"
+complete -c widots -n "__fish_seen_subcommand_from brew; and __fish_seen_subcommand_from import" -s p -l profile -r -d "The profile whose packages to import"
 
 # fish
 complete -f -c widots -n "__fish_seen_subcommand_from fish" -a "install" -d "Install Fish shell"
@@ -38,3 +123,4 @@ complete -f -c widots -n "__fish_seen_subcommand_from fish" -a "fisher" -d "Inst
 complete -f -c widots -n "__fish_seen_subcommand_from vscode" -a "import" -d "Import VS Code extensions"
 complete -f -c widots -n "__fish_seen_subcommand_from vscode" -a "export" -d "Export VS Code extensions"
 complete -f -c widots -n "__fish_seen_subcommand_from vscode" -a "code" -d "Ensure code command is available"
+complete -c widots -n "__fish_seen_subcommand_from vscode; and __fish_seen_subcommand_from import" -s p -l profile -r -d "The profile whose extensions to import"
@@ -0,0 +1,73 @@
+use crate::domain::path::PathOperations;
+use crate::error::AppError;
+use crate::models::config::{Config, Link};
+use crate::models::link::LinkOptions;
+use crate::utils::toml::TomlOperations;
+use std::path::{Path, PathBuf};
+
+/// Parses the config and keeps what applies in `profile`, or else in the profile that lists
+/// this host. Returns the config along with the profile it was selected for.
+pub(crate) async fn select_config(
+    path_operations: &dyn PathOperations,
+    toml_parser: &dyn TomlOperations,
+    config_path: &Path,
+    profile: Option<&str>,
+) -> Result<(Config, Option<String>), AppError> {
+    let config_path = path_operations.parse_path(config_path).await?;
+    let config = toml_parser.parse(&config_path).await?;
+    let profile = match profile {
+        Some(profile) => Some(profile.to_string()),
+        None if config
+            .profile
+            .values()
+            .any(|profile| !profile.hosts.is_empty()) =>
+        {
+            let hostname = whoami::fallible::hostname().map_err(AppError::Io)?;
+            config.host_profile(&hostname)?
+        }
+        None => None,
+    };
+    Ok((config.select(profile.as_deref())?, profile))
+}
+
+/// Selects the config like `select_config`, or an empty one when there is no config file and no
+/// profile was asked for.
+pub(crate) async fn select_config_if_any(
+    path_operations: &dyn PathOperations,
+    toml_parser: &dyn TomlOperations,
+    config_path: &Path,
+    profile: Option<&str>,
+) -> Result<Config, AppError> {
+    match select_config(path_operations, toml_parser, config_path, profile).await {
+        Err(AppError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound && profile.is_none() => {
+            Ok(Config::default())
+        }
+        selected => Ok(selected?.0),
+    }
+}
+
+/// Where a `[[link]]` entry links into, with its options. `~` in its target is `target`, and a
+/// run into somewhere other than the home directory, such as `--test`, moves an absolute target
+/// below `target` too, where it needs no sudo.
+pub(crate) async fn link_placement(
+    path_operations: &dyn PathOperations,
+    link: &Link,
+    target: &Path,
+) -> Result<(PathBuf, LinkOptions), AppError> {
+    let mut options = link.options();
+    let Some(link_target) = &link.target else {
+        return Ok((target.to_path_buf(), options));
+    };
+    if link_target.is_absolute() {
+        if target == path_operations.get_home_dir().await? {
+            return Ok((link_target.clone(), options));
+        }
+        options.sudo = false;
+    }
+
+    let relative = link_target
+        .strip_prefix("~")
+        .or_else(|_| link_target.strip_prefix("/"))
+        .unwrap_or(link_target);
+    Ok((target.join(relative), options))
+}
@@ -1,2 +1,3 @@
+pub mod config;
 pub mod service_provider;
 pub mod services;
@@ -8,20 +8,31 @@ use crate::application::services::link_service::LinkService;
 use crate::application::services::link_service::LinkServiceImpl;
 use crate::application::services::load_service::LoadService;
 use crate::application::services::load_service::LoadServiceImpl;
+use crate::application::services::sync_service::SyncService;
+use crate::application::services::sync_service::SyncServiceImpl;
 use crate::application::services::vscode_service::VSCodeService;
 use crate::application::services::vscode_service::VSCodeServiceImpl;
+#[cfg(test)]
+use crate::constants::{BACKUPS_DIR, IDENTITY_FILENAME, STATE_FILENAME};
+use crate::domain::backup::BackupOperations;
 use crate::domain::link::LinkOperations;
 use crate::domain::os::OSOperations;
 use crate::domain::path::PathOperations;
 use crate::domain::prompt::PromptOperations;
 use crate::domain::shell::ShellExecutor;
+use crate::domain::state::StateOperations;
 use crate::error::AppError;
+use crate::infrastructure::backup::BackupStore;
 use crate::infrastructure::fs::{FileSystemOperations, FileSystemOperationsImpl};
 use crate::infrastructure::link::LinkerImpl;
 use crate::infrastructure::os::OSDetector;
 use crate::infrastructure::path::PathExpander;
+use crate::infrastructure::permission::PermissionManager;
 use crate::infrastructure::prompt::Prompt;
+use crate::infrastructure::secret::AgeSecrets;
 use crate::infrastructure::shell::executor::SystemShellExecutor;
+use crate::infrastructure::state::StateStore;
+use crate::infrastructure::watch::NotifyWatcher;
 use crate::utils::toml::{TomlOperations, TomlParser};
 use std::sync::Arc;
 
@@ -32,6 +43,7 @@ pub trait ServiceProvider: Send + Sync {
     fn brew_service(&self) -> Arc<dyn BrewService>;
     fn fish_service(&self) -> Arc<dyn FishService>;
     fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+    fn sync_service(&self) -> Arc<dyn SyncService>;
 }
 
 pub struct ProductionServiceProvider {
@@ -41,6 +53,7 @@ pub struct ProductionServiceProvider {
     brew_service: Arc<dyn BrewService>,
     fish_service: Arc<dyn FishService>,
     vscode_service: Arc<dyn VSCodeService>,
+    sync_service: Arc<dyn SyncService>,
 }
 
 impl ProductionServiceProvider {
@@ -52,13 +65,29 @@ impl ProductionServiceProvider {
         let path_operations: Arc<dyn PathOperations> = Arc::new(PathExpander::new());
         let toml_parser: Arc<dyn TomlOperations> = Arc::new(TomlParser::new());
         let prompter: Arc<dyn PromptOperations> = Arc::new(Prompt::new(false)); // false for production
-        let link_operations: Arc<dyn LinkOperations> = Arc::new(LinkerImpl::new());
+        let backup_operations: Arc<dyn BackupOperations> = Arc::new(BackupStore::new(
+            BackupStore::default_root()?,
+            shell_executor.clone(),
+        ));
+        let state_operations: Arc<dyn StateOperations> =
+            Arc::new(StateStore::new(StateStore::default_path()?));
+        let link_operations: Arc<dyn LinkOperations> = Arc::new(LinkerImpl::new(
+            backup_operations.clone(),
+            prompter.clone(),
+            state_operations.clone(),
+            Arc::new(AgeSecrets::new(AgeSecrets::default_path()?)),
+            shell_executor.clone(),
+        ));
 
         Ok(Self {
             link_service: Arc::new(LinkServiceImpl::new(
                 link_operations.clone(),
                 path_operations.clone(),
+                toml_parser.clone(),
                 prompter.clone(),
+                backup_operations.clone(),
+                os_detector.clone(),
+                Arc::new(NotifyWatcher::new()),
             )),
             load_service: Arc::new(LoadServiceImpl::new(
                 link_operations.clone(),
@@ -67,6 +96,7 @@ impl ProductionServiceProvider {
                 os_detector.clone(),
                 shell_executor.clone(),
                 prompter.clone(),
+                Arc::new(PermissionManager::new()),
             )),
             deploy_service: Arc::new(DeployServiceImpl::new(
                 shell_executor.clone(),
@@ -76,6 +106,8 @@ impl ProductionServiceProvider {
             brew_service: Arc::new(BrewServiceImpl::new(
                 shell_executor.clone(),
                 fs_operations.clone(),
+                path_operations.clone(),
+                toml_parser.clone(),
             )),
             fish_service: Arc::new(FishServiceImpl::new(
                 shell_executor.clone(),
@@ -85,6 +117,12 @@ impl ProductionServiceProvider {
                 shell_executor.clone(),
                 fs_operations.clone(),
                 os_detector.clone(),
+                path_operations.clone(),
+                toml_parser.clone(),
+            )),
+            sync_service: Arc::new(SyncServiceImpl::new(
+                shell_executor.clone(),
+                path_operations.clone(),
             )),
         })
     }
@@ -114,16 +152,23 @@ impl ServiceProvider for ProductionServiceProvider {
     fn vscode_service(&self) -> Arc<dyn VSCodeService> {
         self.vscode_service.clone()
     }
+
+    fn sync_service(&self) -> Arc<dyn SyncService> {
+        self.sync_service.clone()
+    }
 }
 
 #[cfg(test)]
 pub struct TestServiceProvider {
+    /// Holds the state, backups and identity of this provider alone, removed along with it.
+    _state_dir: tempfile::TempDir,
     link_service: Arc<dyn LinkService>,
     load_service: Arc<dyn LoadService>,
     deploy_service: Arc<dyn DeployService>,
     brew_service: Arc<dyn BrewService>,
     fish_service: Arc<dyn FishService>,
     vscode_service: Arc<dyn VSCodeService>,
+    sync_service: Arc<dyn SyncService>,
 }
 
 #[cfg(test)]
@@ -136,13 +181,31 @@ impl TestServiceProvider {
         let path_operations: Arc<dyn PathOperations> = Arc::new(PathExpander::new());
         let toml_parser: Arc<dyn TomlOperations> = Arc::new(TomlParser::new());
         let prompter: Arc<dyn PromptOperations> = Arc::new(Prompt::new(force));
-        let link_operations: Arc<dyn LinkOperations> = Arc::new(LinkerImpl::new());
+        let state_dir = tempfile::TempDir::new().expect("a temporary state directory");
+        let backup_operations: Arc<dyn BackupOperations> = Arc::new(BackupStore::new(
+            state_dir.path().join(BACKUPS_DIR),
+            shell_executor.clone(),
+        ));
+        let state_operations: Arc<dyn StateOperations> =
+            Arc::new(StateStore::new(state_dir.path().join(STATE_FILENAME)));
+        let link_operations: Arc<dyn LinkOperations> = Arc::new(LinkerImpl::new(
+            backup_operations.clone(),
+            prompter.clone(),
+            state_operations.clone(),
+            Arc::new(AgeSecrets::new(state_dir.path().join(IDENTITY_FILENAME))),
+            shell_executor.clone(),
+        ));
 
         Self {
+            _state_dir: state_dir,
             link_service: Arc::new(LinkServiceImpl::new(
                 link_operations.clone(),
                 path_operations.clone(),
+                toml_parser.clone(),
                 prompter.clone(),
+                backup_operations.clone(),
+                os_detector.clone(),
+                Arc::new(NotifyWatcher::new()),
             )),
             load_service: Arc::new(LoadServiceImpl::new(
                 link_operations.clone(),
@@ -151,6 +214,7 @@ impl TestServiceProvider {
                 os_detector.clone(),
                 shell_executor.clone(),
                 prompter.clone(),
+                Arc::new(PermissionManager::new()),
             )),
             deploy_service: Arc::new(DeployServiceImpl::new(
                 shell_executor.clone(),
@@ -160,6 +224,8 @@ impl TestServiceProvider {
             brew_service: Arc::new(BrewServiceImpl::new(
                 shell_executor.clone(),
                 fs_operations.clone(),
+                path_operations.clone(),
+                toml_parser.clone(),
             )),
             fish_service: Arc::new(FishServiceImpl::new(
                 shell_executor.clone(),
@@ -169,6 +235,12 @@ impl TestServiceProvider {
                 shell_executor.clone(),
                 fs_operations.clone(),
                 os_detector.clone(),
+                path_operations.clone(),
+                toml_parser.clone(),
+            )),
+            sync_service: Arc::new(SyncServiceImpl::new(
+                shell_executor.clone(),
+                path_operations.clone(),
             )),
         }
     }
@@ -199,6 +271,10 @@ impl ServiceProvider for TestServiceProvider {
     fn vscode_service(&self) -> Arc<dyn VSCodeService> {
         self.vscode_service.clone()
     }
+
+    fn sync_service(&self) -> Arc<dyn SyncService> {
+        self.sync_service.clone()
+    }
 }
 
 #[cfg(test)]
@@ -225,6 +301,7 @@ mod tests {
         assert!(Arc::strong_count(&provider.brew_service()) > 0);
         assert!(Arc::strong_count(&provider.fish_service()) > 0);
         assert!(Arc::strong_count(&provider.vscode_service()) > 0);
+        assert!(Arc::strong_count(&provider.sync_service()) > 0);
     }
 
     #[test]
@@ -236,6 +313,7 @@ mod tests {
         assert!(Arc::strong_count(&provider.brew_service()) > 0);
         assert!(Arc::strong_count(&provider.fish_service()) > 0);
         assert!(Arc::strong_count(&provider.vscode_service()) > 0);
+        assert!(Arc::strong_count(&provider.sync_service()) > 0);
     }
 
     #[test]
@@ -247,5 +325,6 @@ mod tests {
         assert!(Arc::strong_count(&provider.brew_service()) > 0);
         assert!(Arc::strong_count(&provider.fish_service()) > 0);
         assert!(Arc::strong_count(&provider.vscode_service()) > 0);
+        assert!(Arc::strong_count(&provider.sync_service()) > 0);
     }
 }
@@ -1,7 +1,10 @@
+use crate::application::config::select_config_if_any;
 use crate::constants::{BREW_CASK_FORMULA_FILENAME, BREW_FORMULA_FILENAME, RESOURCES_DIR};
+use crate::domain::path::PathOperations;
 use crate::domain::shell::ShellExecutor;
 use crate::error::AppError;
 use crate::infrastructure::fs::FileSystemOperations;
+use crate::utils::toml::TomlOperations;
 use async_trait::async_trait;
 use std::path::Path;
 use std::sync::Arc;
@@ -9,23 +12,33 @@ use std::sync::Arc;
 #[async_trait]
 pub trait BrewService: Send + Sync {
     async fn install(&self) -> Result<(), AppError>;
-    async fn import(&self) -> Result<(), AppError>;
+    async fn import<'a>(
+        &self,
+        config_path: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<(), AppError>;
     async fn export(&self) -> Result<(), AppError>;
 }
 
 pub struct BrewServiceImpl {
     shell_executor: Arc<dyn ShellExecutor>,
     fs_operations: Arc<dyn FileSystemOperations>,
+    path_operations: Arc<dyn PathOperations>,
+    toml_parser: Arc<dyn TomlOperations>,
 }
 
 impl BrewServiceImpl {
     pub fn new(
         shell_executor: Arc<dyn ShellExecutor>,
         fs_operations: Arc<dyn FileSystemOperations>,
+        path_operations: Arc<dyn PathOperations>,
+        toml_parser: Arc<dyn TomlOperations>,
     ) -> Self {
         Self {
             shell_executor,
             fs_operations,
+            path_operations,
+            toml_parser,
         }
     }
 }
@@ -41,9 +54,23 @@ impl BrewService for BrewServiceImpl {
         Ok(())
     }
 
-    async fn import(&self) -> Result<(), AppError> {
+    /// Installs the exported formulas and casks, then those the config lists for `profile`.
+    async fn import<'a>(
+        &self,
+        config_path: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<(), AppError> {
+        let config = select_config_if_any(
+            self.path_operations.as_ref(),
+            self.toml_parser.as_ref(),
+            config_path,
+            profile,
+        )
+        .await?;
+
         let import_path = Path::new(RESOURCES_DIR).join(BREW_FORMULA_FILENAME);
-        let formulas = self.fs_operations.read_lines(import_path.as_path()).await?;
+        let mut formulas = self.fs_operations.read_lines(import_path.as_path()).await?;
+        formulas.extend(config.packages(|package| &package.brew));
         for formula in formulas {
             self.shell_executor
                 .execute("brew", &["install", formula.as_str()])
@@ -51,7 +78,8 @@ impl BrewService for BrewServiceImpl {
         }
 
         let import_path = Path::new(RESOURCES_DIR).join(BREW_CASK_FORMULA_FILENAME);
-        let casks = self.fs_operations.read_lines(import_path.as_path()).await?;
+        let mut casks = self.fs_operations.read_lines(import_path.as_path()).await?;
+        casks.extend(config.packages(|package| &package.cask));
         for cask in casks {
             self.shell_executor
                 .execute("brew", &["install", "--cask", cask.as_str()])
@@ -99,6 +127,8 @@ mod tests {
     use crate::domain::shell::ShellExecutor;
     use crate::error::AppError;
     use crate::infrastructure::fs::FileSystemOperations;
+    use crate::infrastructure::path::PathExpander;
+    use crate::utils::toml::TomlParser;
     use async_trait::async_trait;
     use mockall::mock;
     use std::io::{Error, ErrorKind};
@@ -106,6 +136,8 @@ mod tests {
     use std::process::Output;
     use std::sync::Arc;
 
+    const NO_CONFIG: &str = "/nonexistent/config.toml";
+
     mock! {
         ShellExecutor {}
         #[async_trait]
@@ -137,7 +169,12 @@ mod tests {
             })
             .returning(|_, _| Ok("Homebrew installed successfully".to_string()));
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = brew_service.install().await;
         assert!(result.is_ok());
@@ -156,12 +193,74 @@ mod tests {
             .expect_execute()
             .returning(|_, _| Ok("Package installed successfully".to_string()));
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
-        let result = brew_service.import().await;
+        let result = brew_service.import(Path::new(NO_CONFIG), None).await;
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_brew_import_profile_packages() -> Result<(), AppError> {
+        let mut mock_shell = MockShellExecutor::new();
+        let mut mock_fs = MockFileSystemOperations::new();
+        let config = tempfile::NamedTempFile::new()?;
+        std::fs::write(
+            config.path(),
+            r#"
+            [[package]]
+            brew = ["git"]
+
+            [[package]]
+            brew = ["awscli"]
+            cask = ["slack"]
+            profiles = ["work"]
+            "#,
+        )?;
+
+        mock_fs.expect_read_lines().returning(|path| {
+            Ok(if path.ends_with(BREW_FORMULA_FILENAME) {
+                vec!["wget".to_string()]
+            } else {
+                vec![]
+            })
+        });
+        let mut sequence = mockall::Sequence::new();
+        for expected in [
+            vec!["install", "wget"],
+            vec!["install", "git"],
+            vec!["install", "awscli"],
+            vec!["install", "--cask", "slack"],
+        ] {
+            mock_shell
+                .expect_execute()
+                .withf(move |cmd, args| cmd == "brew" && args == expected.as_slice())
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(|_, _| Ok(String::new()));
+        }
+
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
+        brew_service.import(config.path(), Some("work")).await?;
+
+        // A profile needs a config to come from
+        let result = brew_service
+            .import(Path::new(NO_CONFIG), Some("work"))
+            .await;
+        assert!(matches!(result, Err(AppError::Io(_))));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_brew_export() {
         let mut mock_shell = MockShellExecutor::new();
@@ -173,7 +272,12 @@ mod tests {
 
         mock_fs.expect_write_lines().returning(|_, _| Ok(()));
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = brew_service.export().await;
         assert!(result.is_ok());
@@ -188,7 +292,12 @@ mod tests {
             .expect_execute()
             .returning(|_, _| Err(AppError::ShellExecution("Installation failed".to_string())));
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = brew_service.install().await;
         assert!(result.is_err());
@@ -206,9 +315,14 @@ mod tests {
             )))
         });
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
-        let result = brew_service.import().await;
+        let result = brew_service.import(Path::new(NO_CONFIG), None).await;
         assert!(result.is_err());
     }
 
@@ -230,9 +344,14 @@ mod tests {
                 ))
             });
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
-        let result = brew_service.import().await;
+        let result = brew_service.import(Path::new(NO_CONFIG), None).await;
         assert!(result.is_err());
     }
 
@@ -250,7 +369,12 @@ mod tests {
                 ))
             });
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = brew_service.export().await;
         assert!(result.is_err());
@@ -275,7 +399,12 @@ mod tests {
             )))
         });
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = brew_service.export().await;
         assert!(result.is_err());
@@ -288,9 +417,14 @@ mod tests {
 
         mock_fs.expect_read_lines().returning(|_| Ok(vec![]));
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
-        let result = brew_service.import().await;
+        let result = brew_service.import(Path::new(NO_CONFIG), None).await;
         assert!(result.is_ok());
     }
 
@@ -304,7 +438,12 @@ mod tests {
             .returning(|_, _| Ok("".to_string()));
         mock_fs.expect_write_lines().returning(|_, _| Ok(()));
 
-        let brew_service = BrewServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs));
+        let brew_service = BrewServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = brew_service.export().await;
         assert!(result.is_ok());
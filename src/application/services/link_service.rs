@@ -1,11 +1,25 @@
+use crate::application::config::{link_placement, select_config_if_any};
+use crate::constants::WATCH_DEBOUNCE_MS;
+use crate::domain::backup::BackupOperations;
 use crate::domain::link::LinkOperations;
+use crate::domain::os::OSOperations;
 use crate::domain::path::PathOperations;
 use crate::domain::prompt::PromptOperations;
+use crate::domain::watch::WatchOperations;
 use crate::error::AppError;
-use crate::models::link::FileProcessResult;
+use crate::infrastructure::template;
+use crate::models::diff::FileDiff;
+use crate::models::link::{ConflictStrategy, FileProcessResult, LinkOptions};
+use crate::models::plan::LinkPlan;
+use crate::models::status::LinkStatus;
+use crate::utils::toml::TomlOperations;
 use async_trait::async_trait;
-use std::path::Path;
+use log::{debug, error, info, warn};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[async_trait]
 pub trait LinkService: Send + Sync {
@@ -13,40 +27,269 @@ pub trait LinkService: Send + Sync {
         &self,
         source: &Path,
         target: &Path,
+        options: &LinkOptions,
     ) -> Result<Vec<FileProcessResult>, AppError>;
-    async fn materialize_dotfiles(&self, target: &Path)
-        -> Result<Vec<FileProcessResult>, AppError>;
+    async fn plan_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<LinkPlan, AppError>;
+    async fn unlink_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+    async fn plan_unlink_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+    ) -> Result<LinkPlan, AppError>;
+    async fn status_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<Vec<LinkStatus>, AppError>;
+    async fn diff_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+        paths: &[PathBuf],
+    ) -> Result<Vec<FileDiff>, AppError>;
+    async fn materialize_dotfiles(
+        &self,
+        target: &Path,
+        source: Option<PathBuf>,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+    async fn restore_backup(
+        &self,
+        run_id: Option<String>,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+    async fn list_backups(&self) -> Result<Vec<String>, AppError>;
+    async fn add_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        paths: &[PathBuf],
+        options: &LinkOptions,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+    async fn encrypt_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        paths: &[PathBuf],
+        options: &LinkOptions,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+    async fn prune_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+    async fn plan_prune_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<LinkPlan, AppError>;
+    async fn watch_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<(), AppError>;
+    async fn config_vars(&self, config_path: &Path) -> Result<BTreeMap<String, String>, AppError>;
+    /// The target and options the `[[link]]` of the config for `source` links with in `profile`,
+    /// or `target` with just the `[vars]` when no entry links from `source`.
+    async fn configured_link<'a>(
+        &self,
+        config_path: &'a Path,
+        profile: Option<&'a str>,
+        source: &'a Path,
+        target: &'a Path,
+    ) -> Result<(PathBuf, LinkOptions), AppError>;
 }
 
 pub struct LinkServiceImpl {
     link_operations: Arc<dyn LinkOperations>,
     path_operations: Arc<dyn PathOperations>,
+    toml_parser: Arc<dyn TomlOperations>,
     prompter: Arc<dyn PromptOperations>,
+    backup_operations: Arc<dyn BackupOperations>,
+    os_detector: Arc<dyn OSOperations>,
+    watch_operations: Arc<dyn WatchOperations>,
 }
 
 impl LinkServiceImpl {
     pub fn new(
         link_operations: Arc<dyn LinkOperations>,
         path_operations: Arc<dyn PathOperations>,
+        toml_parser: Arc<dyn TomlOperations>,
         prompter: Arc<dyn PromptOperations>,
+        backup_operations: Arc<dyn BackupOperations>,
+        os_detector: Arc<dyn OSOperations>,
+        watch_operations: Arc<dyn WatchOperations>,
     ) -> Self {
         Self {
             link_operations,
             path_operations,
+            toml_parser,
             prompter,
+            backup_operations,
+            os_detector,
+            watch_operations,
+        }
+    }
+
+    /// Adds the built-in template variables to `options`, keeping any it already sets, such as
+    /// the `[vars]` of the config.
+    async fn with_template_vars(&self, options: &LinkOptions) -> Result<LinkOptions, AppError> {
+        let mut vars =
+            template::builtin_vars(self.os_detector.as_ref(), self.path_operations.as_ref())
+                .await?;
+        vars.extend(options.vars.clone());
+        Ok(LinkOptions {
+            vars,
+            ..options.clone()
+        })
+    }
+
+    /// Links and renders everything `source` produces and removes what it no longer does,
+    /// without asking. Used while watching, where nobody is there to answer.
+    async fn sync_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<(), AppError> {
+        let link_operations = self.link_operations.as_ref();
+        let results = in_run(link_operations, async {
+            let mut results = link_operations
+                .link_recursively(source, target, options)
+                .await?;
+
+            let prune = link_operations.plan_prune(source, target, options).await?;
+            if !prune.actions.is_empty() {
+                results.extend(link_operations.apply_plan(&prune).await?);
+            }
+            Ok(results)
+        })
+        .await?;
+
+        for result in results {
+            match result {
+                FileProcessResult::Linked(src, dst) => {
+                    debug!("Linked: {} -> {}", src.display(), dst.display())
+                }
+                FileProcessResult::Rendered(src, dst) => {
+                    debug!("Rendered: {} -> {}", src.display(), dst.display())
+                }
+                FileProcessResult::Decrypted(src, dst) => {
+                    debug!("Decrypted: {} -> {}", src.display(), dst.display())
+                }
+                FileProcessResult::Copied(src, dst) => {
+                    info!("Copied: {} -> {}", src.display(), dst.display())
+                }
+                FileProcessResult::Created(path) => info!("Created: {}", path.display()),
+                FileProcessResult::Unlinked(link, _) => info!("Unlinked: {}", link.display()),
+                FileProcessResult::Removed(path) => info!("Removed: {}", path.display()),
+                FileProcessResult::BackedUp(path, backup) => {
+                    info!("Backed up: {} -> {}", path.display(), backup.display())
+                }
+                FileProcessResult::Conflicted(path, strategy) => {
+                    warn!("Conflict: {} (resolved with {})", path.display(), strategy)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `work` as one linker run, so that its changes are rolled back together if it fails.
+pub(crate) async fn in_run<T>(
+    link_operations: &dyn LinkOperations,
+    work: impl Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    link_operations.begin_run().await?;
+    match work.await {
+        Ok(value) => {
+            link_operations.commit_run().await?;
+            Ok(value)
         }
+        Err(e) => Err(link_operations.rollback_run(e).await),
     }
 }
 
+/// Refuses to link from `/` or the home directory itself, which would take over every file in
+/// it, or into a target inside the source, which linking would recurse through.
+pub(crate) async fn check_link_paths(
+    path_operations: &dyn PathOperations,
+    source: &Path,
+    target: &Path,
+) -> Result<(), AppError> {
+    let home = path_operations.get_home_dir().await?;
+    let home = path_operations.parse_path(&home).await?;
+    if source.parent().is_none() || source == home {
+        return Err(AppError::UnsafeSource(source.to_path_buf()));
+    }
+    if target.starts_with(source) {
+        return Err(AppError::TargetInsideSource(
+            source.to_path_buf(),
+            target.to_path_buf(),
+        ));
+    }
+    Ok(())
+}
+
+/// Removes the stale entries in a prune plan once confirmed. Nothing is asked if there are none.
+pub(crate) async fn apply_prune(
+    link_operations: &dyn LinkOperations,
+    prompter: &dyn PromptOperations,
+    plan: &LinkPlan,
+) -> Result<Vec<FileProcessResult>, AppError> {
+    if plan.actions.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let stale: Vec<String> = plan
+        .actions
+        .iter()
+        .map(|action| format!("  {}", action))
+        .collect();
+    // A plan without a source prunes what sources that left the config had linked
+    let heading = if plan.source.as_os_str().is_empty() {
+        "Sources that are no longer configured linked these entries".to_string()
+    } else {
+        format!("{:?} no longer links these entries", plan.source.display())
+    };
+    if !prompter
+        .confirm_action(&format!(
+            "{}:\n{}\nDo you want to remove them?",
+            heading,
+            stale.join("\n")
+        ))
+        .await?
+    {
+        return Ok(vec![]);
+    }
+
+    link_operations.apply_plan(plan).await
+}
+
 #[async_trait]
 impl LinkService for LinkServiceImpl {
     async fn link_dotfiles(
         &self,
         source: &Path,
         target: &Path,
+        options: &LinkOptions,
     ) -> Result<Vec<FileProcessResult>, AppError> {
         let source = self.path_operations.parse_path(source).await?;
         let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
 
         let ans = self
             .prompter
@@ -60,17 +303,31 @@ impl LinkService for LinkServiceImpl {
             return Ok(vec![]);
         }
 
-        let mut results = self
-            .link_operations
-            .link_recursively(&source, &target)
-            .await?;
+        let options = self.with_template_vars(options).await?;
+        let link_operations = self.link_operations.as_ref();
+        let mut results = in_run(link_operations, async {
+            let mut results = link_operations
+                .link_recursively(&source, &target, &options)
+                .await?;
+
+            let prune = link_operations
+                .plan_prune(&source, &target, &options)
+                .await?;
+            results.extend(apply_prune(link_operations, self.prompter.as_ref(), &prune).await?);
+            Ok(results)
+        })
+        .await?;
 
         // Add existing target files to results if they're not already included
         let mut target_entries = tokio::fs::read_dir(target).await?;
         while let Some(entry) = target_entries.next_entry().await? {
             let path = entry.path();
             if !results.iter().any(|r| match r {
-                FileProcessResult::Linked(_, dst) | FileProcessResult::Skipped(dst) => dst == &path,
+                FileProcessResult::Linked(_, dst)
+                | FileProcessResult::Rendered(_, dst)
+                | FileProcessResult::Decrypted(_, dst)
+                | FileProcessResult::Copied(_, dst)
+                | FileProcessResult::Skipped(dst) => dst == &path,
                 _ => false,
             }) {
                 results.push(FileProcessResult::Skipped(path));
@@ -80,11 +337,121 @@ impl LinkService for LinkServiceImpl {
         Ok(results)
     }
 
+    async fn plan_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<LinkPlan, AppError> {
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+
+        let options = self.with_template_vars(options).await?;
+        let mut plan = self
+            .link_operations
+            .plan_links(&source, &target, &options)
+            .await?;
+        // Linking then prunes what the source no longer produces
+        let prune = self
+            .link_operations
+            .plan_prune(&source, &target, &options)
+            .await?;
+        plan.actions.extend(prune.actions);
+        Ok(plan)
+    }
+
+    async fn unlink_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+    ) -> Result<Vec<FileProcessResult>, AppError> {
+        let plan = self.plan_unlink_dotfiles(source, target).await?;
+
+        if !self
+            .prompter
+            .confirm_action(&format!(
+                "This will remove links into {:?} from {:?}. Do you want to continue?",
+                plan.source.display(),
+                plan.target.display()
+            ))
+            .await?
+        {
+            return Ok(vec![]);
+        }
+
+        self.link_operations.apply_plan(&plan).await
+    }
+
+    async fn plan_unlink_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+    ) -> Result<LinkPlan, AppError> {
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+
+        self.link_operations.plan_unlinks(&source, &target).await
+    }
+
+    async fn status_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<Vec<LinkStatus>, AppError> {
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+
+        let options = self.with_template_vars(options).await?;
+        self.link_operations
+            .check_status(&source, &target, &options)
+            .await
+    }
+
+    async fn diff_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+        paths: &[PathBuf],
+    ) -> Result<Vec<FileDiff>, AppError> {
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+        let mut filters = Vec::new();
+        for path in paths {
+            filters.push(self.path_operations.parse_path(path).await?);
+        }
+
+        let options = self.with_template_vars(options).await?;
+        let diffs = self
+            .link_operations
+            .diff_files(&source, &target, &options)
+            .await?;
+        Ok(diffs
+            .into_iter()
+            .filter(|diff| {
+                filters.is_empty()
+                    || filters
+                        .iter()
+                        .any(|path| diff.target.starts_with(path) || diff.source.starts_with(path))
+            })
+            .collect())
+    }
+
     async fn materialize_dotfiles(
         &self,
         target: &Path,
+        source: Option<PathBuf>,
     ) -> Result<Vec<FileProcessResult>, AppError> {
         let target = self.path_operations.parse_path(target).await?;
+        let source = match source {
+            Some(source) => Some(self.path_operations.parse_path(&source).await?),
+            None => None,
+        };
 
         if !self
             .prompter
@@ -98,9 +465,213 @@ impl LinkService for LinkServiceImpl {
         }
 
         self.link_operations
-            .materialize_symlinks_recursively(&target)
+            .materialize_symlinks_recursively(&target, source)
+            .await
+    }
+
+    async fn restore_backup(
+        &self,
+        run_id: Option<String>,
+    ) -> Result<Vec<FileProcessResult>, AppError> {
+        let run_id = match run_id {
+            Some(run_id) => run_id,
+            None => self
+                .backup_operations
+                .list_runs()
+                .await?
+                .pop()
+                .ok_or_else(|| AppError::BackupNotFound("no backup runs recorded".to_string()))?,
+        };
+
+        if !self
+            .prompter
+            .confirm_action(&format!(
+                "This will restore files backed up in run {}. Do you want to continue?",
+                run_id
+            ))
+            .await?
+        {
+            return Ok(vec![]);
+        }
+
+        self.backup_operations.restore(&run_id).await
+    }
+
+    async fn list_backups(&self) -> Result<Vec<String>, AppError> {
+        self.backup_operations.list_runs().await
+    }
+
+    async fn add_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        paths: &[PathBuf],
+        options: &LinkOptions,
+    ) -> Result<Vec<FileProcessResult>, AppError> {
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+
+        // One run for every path, so a failure puts back the paths added before it too
+        let link_operations = self.link_operations.as_ref();
+        in_run(link_operations, async {
+            let mut results = Vec::new();
+            for path in paths {
+                let path = self.path_operations.expand_tilde(path).await?;
+                results.extend(
+                    link_operations
+                        .add_to_source(&source, &target, &path, options)
+                        .await?,
+                );
+            }
+            Ok(results)
+        })
+        .await
+    }
+
+    async fn encrypt_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        paths: &[PathBuf],
+        options: &LinkOptions,
+    ) -> Result<Vec<FileProcessResult>, AppError> {
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+
+        let mut results = Vec::new();
+        for path in paths {
+            let path = self.path_operations.expand_tilde(path).await?;
+            results.extend(
+                self.link_operations
+                    .encrypt_into_source(&source, &target, &path, options)
+                    .await?,
+            );
+        }
+
+        Ok(results)
+    }
+
+    async fn prune_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<Vec<FileProcessResult>, AppError> {
+        let plan = self.plan_prune_dotfiles(source, target, options).await?;
+        apply_prune(self.link_operations.as_ref(), self.prompter.as_ref(), &plan).await
+    }
+
+    async fn plan_prune_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<LinkPlan, AppError> {
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+
+        let options = self.with_template_vars(options).await?;
+        self.link_operations
+            .plan_prune(&source, &target, &options)
             .await
     }
+
+    async fn watch_dotfiles(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<(), AppError> {
+        if options.on_conflict == ConflictStrategy::Ask {
+            return Err(AppError::Watch(
+                "conflicts cannot be asked about while watching, choose another strategy"
+                    .to_string(),
+            ));
+        }
+        let source = self.path_operations.parse_path(source).await?;
+        let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+        let options = LinkOptions {
+            unattended: true,
+            ..self.with_template_vars(options).await?
+        };
+
+        // Start watching before the first sync, so nothing changed during it is missed
+        let mut changes = self
+            .watch_operations
+            .watch(&source, Duration::from_millis(WATCH_DEBOUNCE_MS))
+            .await?;
+        info!(
+            "Watching {} for changes to link into {}",
+            source.display(),
+            target.display()
+        );
+        self.sync_dotfiles(&source, &target, &options).await?;
+
+        while let Some(paths) = changes.recv().await {
+            for path in &paths {
+                info!("Changed: {}", path.display());
+            }
+            // A broken template or a denied file name should not stop the watch
+            if let Err(e) = self.sync_dotfiles(&source, &target, &options).await {
+                error!("Could not sync {}: {}", source.display(), e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `[vars]` of the config, or none when there is no config file.
+    async fn config_vars(&self, config_path: &Path) -> Result<BTreeMap<String, String>, AppError> {
+        let config = select_config_if_any(
+            self.path_operations.as_ref(),
+            self.toml_parser.as_ref(),
+            config_path,
+            None,
+        )
+        .await?;
+        Ok(config.vars)
+    }
+
+    async fn configured_link<'a>(
+        &self,
+        config_path: &'a Path,
+        profile: Option<&'a str>,
+        source: &'a Path,
+        target: &'a Path,
+    ) -> Result<(PathBuf, LinkOptions), AppError> {
+        let config = select_config_if_any(
+            self.path_operations.as_ref(),
+            self.toml_parser.as_ref(),
+            config_path,
+            profile,
+        )
+        .await?;
+        let source = self.path_operations.parse_path(source).await?;
+        for link in config.link.iter().flatten() {
+            if self.path_operations.parse_path(&link.location).await? == source {
+                let (target, options) =
+                    link_placement(self.path_operations.as_ref(), link, target).await?;
+                return Ok((
+                    target,
+                    LinkOptions {
+                        vars: config.vars,
+                        ..options
+                    },
+                ));
+            }
+        }
+        Ok((
+            target.to_path_buf(),
+            LinkOptions {
+                vars: config.vars,
+                ..Default::default()
+            },
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -108,11 +679,16 @@ mod tests {
     use super::*;
     use crate::application::service_provider::ServiceProvider;
     use crate::application::service_provider::TestServiceProvider;
+    use crate::domain::backup::BackupOperations;
     use crate::domain::link::LinkOperations;
+    use crate::domain::os::OSOperations;
     use crate::domain::path::PathOperations;
     use crate::domain::prompt::PromptOperations;
     use crate::error::AppError;
+    use crate::models::config::Config;
     use crate::models::link::FileProcessResult;
+    use crate::models::plan::{LinkAction, LinkPlan};
+    use crate::models::status::LinkStatus;
     use async_trait::async_trait;
     use mockall::mock;
     use prop::string::string_regex;
@@ -121,6 +697,7 @@ mod tests {
     use std::path::PathBuf;
     use std::sync::Arc;
     use tempfile::TempDir;
+    use tokio::sync::mpsc;
 
     mock! {
         LinkOperations {}
@@ -130,12 +707,34 @@ mod tests {
                 &self,
                 source: &Path,
                 target: &Path,
+                options: &LinkOptions,
             ) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_links(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn plan_unlinks(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+            async fn plan_prune(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn plan_prune_sources(&self, sources: &[PathBuf]) -> Result<LinkPlan, AppError>;
+            async fn check_status(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_files(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileDiff>, AppError>;
+            async fn add_to_source(&self, source: &Path, target: &Path, path: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_into_source(&self, source: &Path, target: &Path, path: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn apply_plan(&self, plan: &LinkPlan) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn begin_run(&self) -> Result<(), AppError>;
+            async fn commit_run(&self) -> Result<(), AppError>;
+            async fn rollback_run(&self, error: AppError) -> AppError;
+            async fn record_permissions(&self, path: &Path) -> Result<(), AppError>;
             async fn materialize_symlinks_recursively(
                 &self,
                 target: &Path,
+                source: Option<PathBuf>,
             ) -> Result<Vec<FileProcessResult>, AppError>;
-            fn should_ignore(&self, path: &Path) -> bool;
+        }
+    }
+
+    mock! {
+        TomlOperations {}
+        #[async_trait]
+        impl TomlOperations for TomlOperations {
+            async fn parse(&self, path: &Path) -> Result<Config, AppError>;
         }
     }
 
@@ -149,14 +748,56 @@ mod tests {
         }
     }
 
+    mock! {
+        BackupOperations {}
+        #[async_trait]
+        impl BackupOperations for BackupOperations {
+            fn new_run_id(&self) -> String;
+            async fn reserve(&self, run_id: &str, path: &Path) -> Result<PathBuf, AppError>;
+            async fn restore(&self, run_id: &str) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn list_runs(&self) -> Result<Vec<String>, AppError>;
+        }
+    }
+
     mock! {
         PromptOperations {}
         #[async_trait]
         impl PromptOperations for PromptOperations {
             async fn confirm_action(&self, message: &str) -> Result<bool, AppError>;
+            async fn select(&self, message: &str, choices: &[String]) -> Result<usize, AppError>;
+        }
+    }
+
+    mock! {
+        OSOperations {}
+        #[async_trait]
+        impl OSOperations for OSOperations {
+            async fn get_os(&self) -> Result<String, AppError>;
+        }
+    }
+
+    mock! {
+        WatchOperations {}
+        #[async_trait]
+        impl WatchOperations for WatchOperations {
+            async fn watch(&self, path: &Path, debounce: Duration) -> Result<mpsc::Receiver<Vec<PathBuf>>, AppError>;
         }
     }
 
+    fn expect_run(mock_link_ops: &mut MockLinkOperations) {
+        mock_link_ops.expect_begin_run().returning(|| Ok(()));
+        mock_link_ops.expect_commit_run().returning(|| Ok(()));
+        mock_link_ops.expect_rollback_run().returning(|e| e);
+    }
+
+    fn mock_os_ops() -> MockOSOperations {
+        let mut mock_os_ops = MockOSOperations::new();
+        mock_os_ops
+            .expect_get_os()
+            .returning(|| Ok("linux".to_string()));
+        mock_os_ops
+    }
+
     #[tokio::test]
     async fn test_link_dotfiles() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -166,40 +807,76 @@ mod tests {
         tokio::fs::create_dir_all(&target_dir).await.unwrap();
 
         let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
         let mut mock_path_ops = MockPathOperations::new();
         let mut mock_prompt_ops = MockPromptOperations::new();
 
         mock_path_ops
             .expect_parse_path()
             .returning(move |path| Ok(temp_dir.path().join(path)));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
 
         mock_prompt_ops
             .expect_confirm_action()
             .returning(|_| Ok(true));
 
-        mock_link_ops.expect_link_recursively().returning(|_, _| {
-            Ok(vec![
-                FileProcessResult::Linked(
-                    PathBuf::from("/source/file1"),
-                    PathBuf::from("/target/file1"),
-                ),
-                FileProcessResult::Created(PathBuf::from("/target/dir1")),
-            ])
-        });
+        mock_link_ops
+            .expect_link_recursively()
+            .withf(|_, _, options| {
+                options.vars["os"] == "linux" && options.vars["home"] == "/home/user"
+            })
+            .returning(|_, _, _| {
+                Ok(vec![
+                    FileProcessResult::Linked(
+                        PathBuf::from("/source/file1"),
+                        PathBuf::from("/target/file1"),
+                    ),
+                    FileProcessResult::Created(PathBuf::from("/target/dir1")),
+                ])
+            });
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|source, target, options| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![LinkAction::Unlink(target.join("old"), source.join("old"))],
+                    options: options.clone(),
+                })
+            });
+        mock_link_ops
+            .expect_apply_plan()
+            .times(1)
+            .returning(|plan| {
+                Ok(vec![FileProcessResult::Unlinked(
+                    plan.target.join("old"),
+                    plan.source.join("old"),
+                )])
+            });
 
         let link_service = LinkServiceImpl::new(
             Arc::new(mock_link_ops),
             Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
         );
 
         let result = link_service
-            .link_dotfiles(Path::new("source"), Path::new("target"))
+            .link_dotfiles(
+                Path::new("source"),
+                Path::new("target"),
+                &LinkOptions::default(),
+            )
             .await;
 
         assert!(result.is_ok());
         let file_results = result.unwrap();
-        assert_eq!(file_results.len(), 2);
+        assert_eq!(file_results.len(), 3);
     }
 
     #[tokio::test]
@@ -218,7 +895,8 @@ mod tests {
 
         mock_link_ops
             .expect_materialize_symlinks_recursively()
-            .returning(|_| {
+            .withf(|_, source| source.as_deref() == Some(Path::new("/source")))
+            .returning(|_, _| {
                 Ok(vec![FileProcessResult::Materialized(
                     PathBuf::from("/target/file1"),
                     PathBuf::from("/source/file1"),
@@ -228,11 +906,15 @@ mod tests {
         let link_service = LinkServiceImpl::new(
             Arc::new(mock_link_ops),
             Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
         );
 
         let result = link_service
-            .materialize_dotfiles(Path::new("/target"))
+            .materialize_dotfiles(Path::new("/target"), Some(PathBuf::from("/source")))
             .await;
 
         assert!(result.is_ok());
@@ -249,6 +931,7 @@ mod tests {
         std::fs::create_dir_all(&target).unwrap();
 
         let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
         let mut mock_path_ops = MockPathOperations::new();
         let mut mock_prompt = MockPromptOperations::new();
 
@@ -256,23 +939,459 @@ mod tests {
             .expect_parse_path()
             .returning(|path| Ok(path.to_path_buf()));
 
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+
         mock_prompt.expect_confirm_action().returning(|_| Ok(true));
 
         mock_link_ops
             .expect_link_recursively()
-            .returning(|_, _| Ok(vec![]));
+            .returning(|_, _, _| Ok(vec![]));
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|_, _, _| Ok(LinkPlan::default()));
+        mock_link_ops.expect_apply_plan().times(0);
 
         let link_service = LinkServiceImpl::new(
             Arc::new(mock_link_ops),
             Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
             Arc::new(mock_prompt),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
         );
 
-        let result = link_service.link_dotfiles(&source, &target).await;
+        let result = link_service
+            .link_dotfiles(&source, &target, &LinkOptions::default())
+            .await;
         assert!(result.is_ok());
         assert!(result.unwrap().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_prune_dotfiles_user_cancellation() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_prompt = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+
+        mock_prompt
+            .expect_confirm_action()
+            .withf(|message| message.contains("Remove: /target/.gitconfig"))
+            .times(1)
+            .returning(|_| Ok(false));
+
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|source, target, options| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![LinkAction::Remove(target.join(".gitconfig"))],
+                    options: options.clone(),
+                })
+            });
+        mock_link_ops.expect_apply_plan().times(0);
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let result = link_service
+            .prune_dotfiles(
+                Path::new("/source"),
+                Path::new("/target"),
+                &LinkOptions::default(),
+            )
+            .await;
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plan_dotfiles_includes_the_prune() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_prompt = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+        mock_prompt.expect_confirm_action().times(0);
+
+        mock_link_ops
+            .expect_plan_links()
+            .returning(|source, target, options| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![LinkAction::Link(
+                        source.join(".bashrc"),
+                        target.join(".bashrc"),
+                    )],
+                    options: options.clone(),
+                })
+            });
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|source, target, options| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![LinkAction::Remove(target.join(".gitconfig"))],
+                    options: options.clone(),
+                })
+            });
+        mock_link_ops.expect_apply_plan().times(0);
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let plan = link_service
+            .plan_dotfiles(
+                Path::new("/source"),
+                Path::new("/target"),
+                &LinkOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            plan.actions,
+            vec![
+                LinkAction::Link(
+                    PathBuf::from("/source/.bashrc"),
+                    PathBuf::from("/target/.bashrc"),
+                ),
+                LinkAction::Remove(PathBuf::from("/target/.gitconfig")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_dotfiles_syncs_on_each_change() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_prompt = MockPromptOperations::new();
+        let mut mock_watch = MockWatchOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+        mock_prompt.expect_confirm_action().times(0);
+
+        // One batch of changes, then the watcher stops
+        mock_watch
+            .expect_watch()
+            .withf(|path, _| path == Path::new("/source"))
+            .times(1)
+            .returning(|source, _| {
+                let (tx, rx) = mpsc::channel(1);
+                tx.try_send(vec![source.join(".vimrc")]).unwrap();
+                Ok(rx)
+            });
+
+        // Once when the watch starts and once for the change, never stopping to ask
+        mock_link_ops
+            .expect_link_recursively()
+            .withf(|_, _, options| options.unattended)
+            .times(2)
+            .returning(|_, _, _| Ok(vec![]));
+        let mut syncs = 0;
+        mock_link_ops
+            .expect_plan_prune()
+            .times(2)
+            .returning(move |source, target, options| {
+                syncs += 1;
+                let actions = if syncs == 2 {
+                    vec![LinkAction::Unlink(
+                        target.join(".vimrc"),
+                        source.join(".vimrc"),
+                    )]
+                } else {
+                    vec![]
+                };
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions,
+                    options: options.clone(),
+                })
+            });
+        mock_link_ops
+            .expect_apply_plan()
+            .withf(|plan| plan.actions.len() == 1)
+            .times(1)
+            .returning(|_| Ok(vec![]));
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(mock_watch),
+        );
+
+        let result = link_service
+            .watch_dotfiles(
+                Path::new("/source"),
+                Path::new("/target"),
+                &LinkOptions::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        let result = link_service
+            .watch_dotfiles(
+                Path::new("/source"),
+                Path::new("/target"),
+                &LinkOptions {
+                    on_conflict: ConflictStrategy::Ask,
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::Watch(_))));
+    }
+
+    #[tokio::test]
+    async fn test_link_dotfiles_refuses_unsafe_paths() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+        mock_prompt_ops.expect_confirm_action().times(0);
+        mock_link_ops.expect_link_recursively().times(0);
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+        let options = LinkOptions::default();
+
+        for source in ["/", "/home/user"] {
+            let result = link_service
+                .link_dotfiles(Path::new(source), Path::new("/mnt/home"), &options)
+                .await;
+            assert!(
+                matches!(&result, Err(AppError::UnsafeSource(path)) if path == Path::new(source))
+            );
+        }
+
+        for target in ["/home/user/dotfiles", "/home/user/dotfiles/home"] {
+            let result = link_service
+                .link_dotfiles(
+                    Path::new("/home/user/dotfiles"),
+                    Path::new(target),
+                    &options,
+                )
+                .await;
+            assert!(matches!(result, Err(AppError::TargetInsideSource(_, _))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_dotfiles_adds_every_path_in_one_run() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_expand_tilde()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+
+        let mut sequence = mockall::Sequence::new();
+        mock_link_ops
+            .expect_begin_run()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(()));
+        mock_link_ops
+            .expect_add_to_source()
+            .withf(|_, _, path, options| {
+                path == Path::new("/home/user/.bashrc") && options.deny_chars == ['#']
+            })
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, path, _| {
+                Ok(vec![FileProcessResult::Linked(
+                    PathBuf::new(),
+                    path.to_path_buf(),
+                )])
+            });
+        mock_link_ops
+            .expect_add_to_source()
+            .withf(|_, _, path, _| path == Path::new("/home/user/.vimrc"))
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _, path, _| Err(AppError::FileNotFound(path.to_path_buf())));
+        mock_link_ops.expect_commit_run().times(0);
+        mock_link_ops
+            .expect_rollback_run()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|e| e);
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(MockPromptOperations::new()),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+        let result = link_service
+            .add_dotfiles(
+                Path::new("/home/user/dotfiles"),
+                Path::new("/home/user"),
+                &[
+                    PathBuf::from("/home/user/.bashrc"),
+                    PathBuf::from("/home/user/.vimrc"),
+                ],
+                &LinkOptions {
+                    deny_chars: vec!['#'],
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        assert!(matches!(result, Err(AppError::FileNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_every_entry_point_refuses_unsafe_paths() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_expand_tilde()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+        mock_prompt_ops.expect_confirm_action().times(0);
+        mock_link_ops.expect_plan_unlinks().times(0);
+        mock_link_ops.expect_plan_prune().times(0);
+        mock_link_ops.expect_check_status().times(0);
+        mock_link_ops.expect_diff_files().times(0);
+        mock_link_ops.expect_add_to_source().times(0);
+        mock_link_ops.expect_encrypt_into_source().times(0);
+        mock_link_ops.expect_apply_plan().times(0);
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+        let (source, target) = (Path::new("/"), Path::new("/home/user"));
+        let options = LinkOptions::default();
+        let paths = [PathBuf::from("/home/user/.bashrc")];
+
+        // `widots unlink /` would otherwise remove every link in the home directory
+        let refused = |result: Result<(), AppError>| matches!(result, Err(AppError::UnsafeSource(path)) if path == Path::new("/"));
+        assert!(refused(
+            link_service
+                .unlink_dotfiles(source, target)
+                .await
+                .map(|_| ())
+        ));
+        assert!(refused(
+            link_service
+                .plan_unlink_dotfiles(source, target)
+                .await
+                .map(|_| ())
+        ));
+        assert!(refused(
+            link_service
+                .prune_dotfiles(source, target, &options)
+                .await
+                .map(|_| ())
+        ));
+        assert!(refused(
+            link_service
+                .plan_prune_dotfiles(source, target, &options)
+                .await
+                .map(|_| ())
+        ));
+        assert!(refused(
+            link_service
+                .status_dotfiles(source, target, &options)
+                .await
+                .map(|_| ())
+        ));
+        assert!(refused(
+            link_service
+                .diff_dotfiles(source, target, &options, &[])
+                .await
+                .map(|_| ())
+        ));
+        assert!(refused(
+            link_service
+                .add_dotfiles(source, target, &paths, &options)
+                .await
+                .map(|_| ())
+        ));
+        assert!(refused(
+            link_service
+                .encrypt_dotfiles(source, target, &paths, &options)
+                .await
+                .map(|_| ())
+        ));
+    }
+
     #[tokio::test]
     async fn test_link_dotfiles_user_cancellation() {
         let mock_link_ops = MockLinkOperations::new();
@@ -282,6 +1401,9 @@ mod tests {
         mock_path_ops
             .expect_parse_path()
             .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
 
         mock_prompt_ops
             .expect_confirm_action()
@@ -290,11 +1412,19 @@ mod tests {
         let link_service = LinkServiceImpl::new(
             Arc::new(mock_link_ops),
             Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
         );
 
         let result = link_service
-            .link_dotfiles(Path::new("/source"), Path::new("/target"))
+            .link_dotfiles(
+                Path::new("/source"),
+                Path::new("/target"),
+                &LinkOptions::default(),
+            )
             .await;
 
         assert!(result.is_ok());
@@ -318,15 +1448,19 @@ mod tests {
 
         mock_link_ops
             .expect_materialize_symlinks_recursively()
-            .returning(|_| Ok(vec![]));
+            .returning(|_, _| Ok(vec![]));
 
         let link_service = LinkServiceImpl::new(
             Arc::new(mock_link_ops),
             Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
             Arc::new(mock_prompt),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
         );
 
-        let result = link_service.materialize_dotfiles(&target).await;
+        let result = link_service.materialize_dotfiles(&target, None).await;
         assert!(result.is_ok());
         assert!(result.unwrap().is_empty());
     }
@@ -348,17 +1482,291 @@ mod tests {
         let link_service = LinkServiceImpl::new(
             Arc::new(mock_link_ops),
             Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
         );
 
         let result = link_service
-            .materialize_dotfiles(Path::new("/target"))
+            .materialize_dotfiles(Path::new("/target"), None)
             .await;
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap().len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_unlink_dotfiles() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+
+        mock_prompt_ops
+            .expect_confirm_action()
+            .returning(|_| Ok(true));
+
+        mock_link_ops
+            .expect_plan_unlinks()
+            .times(1)
+            .returning(|source, target| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![LinkAction::Unlink(
+                        target.join(".bashrc"),
+                        source.join(".bashrc"),
+                    )],
+                    ..Default::default()
+                })
+            });
+
+        mock_link_ops
+            .expect_apply_plan()
+            .withf(|plan| plan.actions.len() == 1)
+            .times(1)
+            .returning(|_| {
+                Ok(vec![FileProcessResult::Unlinked(
+                    PathBuf::from("/target/.bashrc"),
+                    PathBuf::from("/source/.bashrc"),
+                )])
+            });
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let result = link_service
+            .unlink_dotfiles(Path::new("/source"), Path::new("/target"))
+            .await;
+
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unlink_dotfiles_user_cancellation() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
+
+        mock_prompt_ops
+            .expect_confirm_action()
+            .returning(|_| Ok(false));
+
+        mock_link_ops
+            .expect_plan_unlinks()
+            .returning(|_, _| Ok(LinkPlan::default()));
+        mock_link_ops.expect_apply_plan().times(0);
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt_ops),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let result = link_service
+            .unlink_dotfiles(Path::new("/source"), Path::new("/target"))
+            .await;
+
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restore_backup_defaults_to_latest_run() {
+        let mut mock_backup_ops = MockBackupOperations::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_prompt_ops
+            .expect_confirm_action()
+            .returning(|_| Ok(true));
+
+        mock_backup_ops.expect_list_runs().returning(|| {
+            Ok(vec![
+                "20240101-000000".to_string(),
+                "20240102-000000".to_string(),
+            ])
+        });
+
+        mock_backup_ops
+            .expect_restore()
+            .withf(|run_id| run_id == "20240102-000000")
+            .times(1)
+            .returning(|_| {
+                Ok(vec![FileProcessResult::Restored(
+                    PathBuf::from("/home/user/.bashrc"),
+                    PathBuf::from("/backups/20240102-000000/home/user/.bashrc"),
+                )])
+            });
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(MockLinkOperations::new()),
+            Arc::new(MockPathOperations::new()),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(mock_prompt_ops),
+            Arc::new(mock_backup_ops),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let result = link_service.restore_backup(None).await;
+        assert_eq!(result.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_restore_backup_without_runs() {
+        let mut mock_backup_ops = MockBackupOperations::new();
+        mock_backup_ops.expect_list_runs().returning(|| Ok(vec![]));
+        mock_backup_ops.expect_restore().times(0);
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(MockLinkOperations::new()),
+            Arc::new(MockPathOperations::new()),
+            Arc::new(MockTomlOperations::new()),
+            Arc::new(MockPromptOperations::new()),
+            Arc::new(mock_backup_ops),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let result = link_service.restore_backup(None).await;
+        assert!(matches!(result, Err(AppError::BackupNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_config_vars() {
+        let mut mock_path_ops = MockPathOperations::new();
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        let mut mock_toml_ops = MockTomlOperations::new();
+        mock_toml_ops
+            .expect_parse()
+            .withf(|path| path == Path::new("/config.toml"))
+            .returning(|_| {
+                Ok(Config {
+                    vars: [("email".to_string(), "alice@example.com".to_string())].into(),
+                    ..Default::default()
+                })
+            });
+        mock_toml_ops.expect_parse().returning(|path| {
+            Err(AppError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                path.display().to_string(),
+            )))
+        });
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(MockLinkOperations::new()),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(MockPromptOperations::new()),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let vars = link_service
+            .config_vars(Path::new("/config.toml"))
+            .await
+            .unwrap();
+        assert_eq!(vars["email"], "alice@example.com");
+
+        // Without a config file there are only the built-in variables
+        let vars = link_service
+            .config_vars(Path::new("/missing.toml"))
+            .await
+            .unwrap();
+        assert!(vars.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_configured_link() {
+        let mut mock_path_ops = MockPathOperations::new();
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+        let mut mock_toml_ops = MockTomlOperations::new();
+        mock_toml_ops.expect_parse().returning(|_| {
+            Ok(Config {
+                link: Some(vec![crate::models::config::Link {
+                    location: PathBuf::from("/dotfiles/config"),
+                    target: Some(PathBuf::from("~/.config")),
+                    exclude: vec!["*.bak".to_string()],
+                    gitignore: true,
+                    ..Default::default()
+                }]),
+                vars: [("email".to_string(), "alice@example.com".to_string())].into(),
+                ..Default::default()
+            })
+        });
+
+        let link_service = LinkServiceImpl::new(
+            Arc::new(MockLinkOperations::new()),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(MockPromptOperations::new()),
+            Arc::new(MockBackupOperations::new()),
+            Arc::new(mock_os_ops()),
+            Arc::new(MockWatchOperations::new()),
+        );
+
+        let (target, options) = link_service
+            .configured_link(
+                Path::new("/config.toml"),
+                None,
+                Path::new("/dotfiles/config"),
+                Path::new("/home/alice"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(target, PathBuf::from("/home/alice/.config"));
+        assert_eq!(options.exclude, vec!["*.bak"]);
+        assert!(options.gitignore);
+        assert_eq!(options.vars["email"], "alice@example.com");
+
+        // A source without an entry keeps the target and only gets the variables
+        let (target, options) = link_service
+            .configured_link(
+                Path::new("/config.toml"),
+                None,
+                Path::new("/elsewhere"),
+                Path::new("/home/alice"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(target, PathBuf::from("/home/alice"));
+        assert!(options.exclude.is_empty());
+        assert_eq!(options.vars["email"], "alice@example.com");
+    }
+
     fn file_name_strategy() -> impl Strategy<Value = String> {
         prop::bool::ANY.prop_flat_map(|has_dot| {
             string_regex("[a-zA-Z][a-zA-Z0-9_]{0,9}")
@@ -399,7 +1807,7 @@ mod tests {
                 }
 
                 let services = TestServiceProvider::new(true);
-                let result = services.link_service().link_dotfiles(&source_path, &target_path).await;
+                let result = services.link_service().link_dotfiles(&source_path, &target_path, &LinkOptions::default()).await;
 
                 match result {
                     Ok(file_results) => {
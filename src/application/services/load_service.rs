@@ -1,13 +1,22 @@
+use crate::application::config::{link_placement, select_config};
+use crate::application::services::link_service::{apply_prune, check_link_paths, in_run};
 use crate::domain::link::LinkOperations;
 use crate::domain::os::OSOperations;
 use crate::domain::path::PathOperations;
+use crate::domain::permission::PermissionOperations;
 use crate::domain::prompt::PromptOperations;
 use crate::domain::shell::ShellExecutor;
 use crate::error::AppError;
-use crate::models::config::Config;
-use crate::models::link::FileProcessResult;
+use crate::infrastructure::permission::exposed_paths;
+use crate::infrastructure::template;
+use crate::models::config::{Config, Provision};
+use crate::models::link::{FileProcessResult, LinkOptions};
+use crate::models::plan::LoadPlan;
+use crate::models::status::LinkStatus;
 use crate::utils::toml::TomlOperations;
 use async_trait::async_trait;
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
@@ -15,7 +24,24 @@ use tempfile::NamedTempFile;
 
 #[async_trait]
 pub trait LoadService: Send + Sync {
-    async fn load(&self, config_path: &Path, target: &Path) -> Result<(), AppError>;
+    async fn load<'a>(
+        &self,
+        config_path: &'a Path,
+        target: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<(), AppError>;
+    async fn plan<'a>(
+        &self,
+        config_path: &'a Path,
+        target: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<LoadPlan, AppError>;
+    async fn check_permissions<'a>(
+        &self,
+        config_path: &'a Path,
+        target: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<Vec<LinkStatus>, AppError>;
 }
 
 pub struct LoadServiceImpl {
@@ -25,6 +51,7 @@ pub struct LoadServiceImpl {
     os_detector: Arc<dyn OSOperations>,
     shell_executor: Arc<dyn ShellExecutor>,
     prompter: Arc<dyn PromptOperations>,
+    permission_operations: Arc<dyn PermissionOperations>,
 }
 
 impl LoadServiceImpl {
@@ -35,6 +62,7 @@ impl LoadServiceImpl {
         os_detector: Arc<dyn OSOperations>,
         shell_executor: Arc<dyn ShellExecutor>,
         prompter: Arc<dyn PromptOperations>,
+        permission_operations: Arc<dyn PermissionOperations>,
     ) -> Self {
         Self {
             link_operations,
@@ -43,29 +71,129 @@ impl LoadServiceImpl {
             os_detector,
             shell_executor,
             prompter,
+            permission_operations,
         }
     }
 
     async fn evaluate_link_section(&self, config: &Config, target: &Path) -> Result<(), AppError> {
+        let mut sources = Vec::new();
         if let Some(links) = &config.link {
+            let vars = self.template_vars(config).await?;
             for link in links {
-                self.link_dotfiles(&link.location, target).await?;
+                let (link_target, options) =
+                    link_placement(self.path_operations.as_ref(), link, target).await?;
+                let options = LinkOptions {
+                    vars: vars.clone(),
+                    loaded: true,
+                    ..options
+                };
+                self.link_dotfiles(&link.location, &link_target, &options)
+                    .await?;
+                sources.push(self.path_operations.parse_path(&link.location).await?);
             }
         }
+
+        // Links of a removed [[link]] or of another profile are not pruned by any entry above
+        let prune = self.link_operations.plan_prune_sources(&sources).await?;
+        apply_prune(
+            self.link_operations.as_ref(),
+            self.prompter.as_ref(),
+            &prune,
+        )
+        .await?;
         Ok(())
     }
 
+    async fn evaluate_permission_section(
+        &self,
+        config: &Config,
+        target: &Path,
+    ) -> Result<(), AppError> {
+        let target = self.path_operations.parse_path(target).await?;
+        if let Some(permissions) = &config.permission {
+            // Journaled, so that a rollback puts back the modes and owners from before
+            let mut changing = BTreeSet::new();
+            for status in self
+                .permission_operations
+                .check(&target, permissions)
+                .await?
+            {
+                if let LinkStatus::WrongMode(path, ..) | LinkStatus::WrongOwner(path, ..) = status {
+                    changing.insert(path);
+                }
+            }
+            for path in &changing {
+                self.link_operations.record_permissions(path).await?;
+            }
+
+            for result in self
+                .permission_operations
+                .apply(&target, permissions)
+                .await?
+            {
+                match result {
+                    FileProcessResult::ModeChanged(path, mode) => {
+                        println!("Changed mode: {} to {:o}", path.display(), mode)
+                    }
+                    FileProcessResult::OwnerChanged(path, owner) => {
+                        println!("Changed owner: {} to {}", path.display(), owner)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for (path, mode) in exposed_paths(&target).await {
+            warn!(
+                "{} is readable by group or others (mode {:o})",
+                path.display(),
+                mode
+            );
+        }
+        Ok(())
+    }
+
+    async fn permission_mismatches(
+        &self,
+        config: &Config,
+        target: &Path,
+    ) -> Result<Vec<LinkStatus>, AppError> {
+        let Some(permissions) = &config.permission else {
+            return Ok(vec![]);
+        };
+
+        let target = self.path_operations.parse_path(target).await?;
+        self.permission_operations.check(&target, permissions).await
+    }
+
+    /// The built-in template variables, overridden by any `[vars]` of the same name.
+    async fn template_vars(&self, config: &Config) -> Result<BTreeMap<String, String>, AppError> {
+        let mut vars =
+            template::builtin_vars(self.os_detector.as_ref(), self.path_operations.as_ref())
+                .await?;
+        vars.extend(config.vars.clone());
+        Ok(vars)
+    }
+
     async fn evaluate_provision_section(&self, config: &Config) -> Result<(), AppError> {
+        for provision in self.matching_provisions(config).await? {
+            println!("🏃 Run provisioning... for {}", provision.mode);
+            self.run_bash_script(&provision.script).await?;
+            println!("🚀 Provisioning done");
+        }
+        Ok(())
+    }
+
+    async fn matching_provisions(&self, config: &Config) -> Result<Vec<Provision>, AppError> {
+        let mut matching = Vec::new();
         if let Some(provisions) = &config.provision {
             for provision in provisions {
                 if provision.mode == self.os_detector.get_os().await? {
-                    println!("🏃 Run provisioning... for {}", provision.mode);
-                    self.run_bash_script(&provision.script).await?;
-                    println!("🚀 Provisioning done");
+                    matching.push(provision.clone());
                 }
             }
         }
-        Ok(())
+        Ok(matching)
     }
 
     async fn run_bash_script(&self, script: &str) -> Result<(), AppError> {
@@ -85,38 +213,142 @@ impl LoadServiceImpl {
         &self,
         source: &Path,
         target: &Path,
+        options: &LinkOptions,
     ) -> Result<Vec<FileProcessResult>, AppError> {
         let source = self.path_operations.parse_path(source).await?;
         let target = self.path_operations.parse_path(target).await?;
+        check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
 
         let ans = self
             .prompter
             .confirm_action(&format!(
-                "This will link files from {:?} to {:?}. Do you want to continue?",
+                "This will link files from {:?} to {:?}{}. Do you want to continue?",
                 source.display(),
-                target.display()
+                target.display(),
+                if options.sudo { " with sudo" } else { "" }
             ))
             .await?;
         if !ans {
             return Ok(vec![]);
         }
 
-        self.link_operations
-            .link_recursively(&source, &target)
-            .await
+        let mut results = self
+            .link_operations
+            .link_recursively(&source, &target, options)
+            .await?;
+
+        let prune = self
+            .link_operations
+            .plan_prune(&source, &target, options)
+            .await?;
+        results.extend(
+            apply_prune(
+                self.link_operations.as_ref(),
+                self.prompter.as_ref(),
+                &prune,
+            )
+            .await?,
+        );
+        Ok(results)
     }
 }
 
 #[async_trait]
 impl LoadService for LoadServiceImpl {
-    async fn load(&self, config_path: &Path, target: &Path) -> Result<(), AppError> {
-        let config = self.toml_parser.parse(config_path).await?;
+    async fn load<'a>(
+        &self,
+        config_path: &'a Path,
+        target: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<(), AppError> {
+        let (config, profile) = select_config(
+            self.path_operations.as_ref(),
+            self.toml_parser.as_ref(),
+            config_path,
+            profile,
+        )
+        .await?;
+        if let Some(profile) = &profile {
+            println!("🧩 Using profile {}", profile);
+        }
 
-        self.evaluate_link_section(&config, target).await?;
+        in_run(self.link_operations.as_ref(), async {
+            self.evaluate_link_section(&config, target).await?;
+            self.evaluate_permission_section(&config, target).await
+        })
+        .await?;
         self.evaluate_provision_section(&config).await?;
 
         Ok(())
     }
+
+    async fn plan<'a>(
+        &self,
+        config_path: &'a Path,
+        target: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<LoadPlan, AppError> {
+        let (config, profile) = select_config(
+            self.path_operations.as_ref(),
+            self.toml_parser.as_ref(),
+            config_path,
+            profile,
+        )
+        .await?;
+
+        let mut links = Vec::new();
+        let mut sources = Vec::new();
+        if let Some(link_entries) = &config.link {
+            let vars = self.template_vars(&config).await?;
+            for link in link_entries {
+                let source = self.path_operations.parse_path(&link.location).await?;
+                let (link_target, options) =
+                    link_placement(self.path_operations.as_ref(), link, target).await?;
+                let options = LinkOptions {
+                    vars: vars.clone(),
+                    loaded: true,
+                    ..options
+                };
+                let target = self.path_operations.parse_path(&link_target).await?;
+                check_link_paths(self.path_operations.as_ref(), &source, &target).await?;
+                let mut plan = self
+                    .link_operations
+                    .plan_links(&source, &target, &options)
+                    .await?;
+                let prune = self
+                    .link_operations
+                    .plan_prune(&source, &target, &options)
+                    .await?;
+                plan.actions.extend(prune.actions);
+                links.push(plan);
+                sources.push(source);
+            }
+        }
+
+        Ok(LoadPlan {
+            profile,
+            links,
+            stale: self.link_operations.plan_prune_sources(&sources).await?,
+            permissions: self.permission_mismatches(&config, target).await?,
+            provisions: self.matching_provisions(&config).await?,
+        })
+    }
+
+    async fn check_permissions<'a>(
+        &self,
+        config_path: &'a Path,
+        target: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<Vec<LinkStatus>, AppError> {
+        let (config, _) = select_config(
+            self.path_operations.as_ref(),
+            self.toml_parser.as_ref(),
+            config_path,
+            profile,
+        )
+        .await?;
+        self.permission_mismatches(&config, target).await
+    }
 }
 
 #[cfg(test)]
@@ -128,8 +360,11 @@ mod test {
     use crate::domain::prompt::PromptOperations;
     use crate::domain::shell::ShellExecutor;
     use crate::error::AppError;
-    use crate::models::config::Config;
-    use crate::models::link::FileProcessResult;
+    use crate::models::config::{Config, Permission};
+    use crate::models::diff::FileDiff;
+    use crate::models::link::{FileProcessResult, LinkOptions};
+    use crate::models::plan::{LinkAction, LinkPlan};
+    use crate::models::status::LinkStatus;
     use crate::utils::toml::TomlOperations;
     use async_trait::async_trait;
     use mockall::mock;
@@ -147,12 +382,26 @@ mod test {
                 &self,
                 source: &Path,
                 target: &Path,
+                options: &LinkOptions,
             ) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_links(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn plan_unlinks(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+            async fn plan_prune(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn plan_prune_sources(&self, sources: &[PathBuf]) -> Result<LinkPlan, AppError>;
+            async fn check_status(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_files(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileDiff>, AppError>;
+            async fn add_to_source(&self, source: &Path, target: &Path, path: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_into_source(&self, source: &Path, target: &Path, path: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn apply_plan(&self, plan: &LinkPlan) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn begin_run(&self) -> Result<(), AppError>;
+            async fn commit_run(&self) -> Result<(), AppError>;
+            async fn rollback_run(&self, error: AppError) -> AppError;
+            async fn record_permissions(&self, path: &Path) -> Result<(), AppError>;
             async fn materialize_symlinks_recursively(
                 &self,
                 target: &Path,
+                source: Option<PathBuf>,
             ) -> Result<Vec<FileProcessResult>, AppError>;
-            fn should_ignore(&self, path: &Path) -> bool;
         }
     }
 
@@ -197,12 +446,32 @@ mod test {
         #[async_trait]
         impl PromptOperations for PromptOperations {
             async fn confirm_action(&self, message: &str) -> Result<bool, AppError>;
+            async fn select(&self, message: &str, choices: &[String]) -> Result<usize, AppError>;
+        }
+    }
+
+    mock! {
+        PermissionOperations {}
+        #[async_trait]
+        impl PermissionOperations for PermissionOperations {
+            async fn check(&self, target: &Path, rules: &[Permission]) -> Result<Vec<LinkStatus>, AppError>;
+            async fn apply(&self, target: &Path, rules: &[Permission]) -> Result<Vec<FileProcessResult>, AppError>;
         }
     }
 
+    fn expect_run(mock_link_ops: &mut MockLinkOperations) {
+        mock_link_ops.expect_begin_run().returning(|| Ok(()));
+        mock_link_ops.expect_commit_run().returning(|| Ok(()));
+        mock_link_ops.expect_rollback_run().returning(|e| e);
+        mock_link_ops
+            .expect_plan_prune_sources()
+            .returning(|_| Ok(LinkPlan::default()));
+    }
+
     #[tokio::test]
     async fn test_load() {
         let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
         let mut mock_path_ops = MockPathOperations::new();
         let mut mock_toml_ops = MockTomlOperations::new();
         let mut mock_os_ops = MockOSOperations::new();
@@ -227,7 +496,10 @@ mod test {
 
         mock_link_ops
             .expect_link_recursively()
-            .returning(|_, _| Ok(vec![]));
+            .returning(|_, _, _| Ok(vec![]));
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|_, _, _| Ok(LinkPlan::default()));
 
         let load_service = LoadServiceImpl::new(
             Arc::new(mock_link_ops),
@@ -236,10 +508,11 @@ mod test {
             Arc::new(mock_os_ops),
             Arc::new(mock_shell),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
         );
 
         let result = load_service
-            .load(Path::new("/config.toml"), Path::new("/target"))
+            .load(Path::new("/config.toml"), Path::new("/target"), None)
             .await;
 
         assert!(result.is_ok());
@@ -247,7 +520,8 @@ mod test {
 
     #[tokio::test]
     async fn test_load_with_provision() {
-        let mock_link_ops = MockLinkOperations::new();
+        let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
         let mut mock_path_ops = MockPathOperations::new();
         let mut mock_toml_ops = MockTomlOperations::new();
         let mut mock_os_ops = MockOSOperations::new();
@@ -263,6 +537,7 @@ mod test {
                 provision: Some(vec![crate::models::config::Provision {
                     mode: "macos".to_string(),
                     script: "echo 'Hello, World!'".to_string(),
+                    ..Default::default()
                 }]),
                 ..Default::default()
             })
@@ -287,10 +562,11 @@ mod test {
             Arc::new(mock_os_ops),
             Arc::new(mock_shell),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
         );
 
         let result = load_service
-            .load(Path::new("/config.toml"), Path::new("/target"))
+            .load(Path::new("/config.toml"), Path::new("/target"), None)
             .await;
 
         assert!(result.is_ok());
@@ -302,7 +578,8 @@ mod test {
         let config_path = temp_file.path().to_path_buf();
         let target = PathBuf::from("/target");
 
-        let mock_link_ops = MockLinkOperations::new();
+        let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
         let mut mock_path_ops = MockPathOperations::new();
         let mut mock_toml_parser = MockTomlOperations::new();
         let mock_os_detector = MockOSOperations::new();
@@ -324,21 +601,25 @@ mod test {
             Arc::new(mock_os_detector),
             Arc::new(mock_shell_executor),
             Arc::new(mock_prompter),
+            Arc::new(MockPermissionOperations::new()),
         );
 
-        let result = load_service.load(&config_path, &target).await;
+        let result = load_service.load(&config_path, &target, None).await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
     async fn test_load_with_invalid_config() {
         let mock_link_ops = MockLinkOperations::new();
-        let mock_path_ops = MockPathOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
         let mut mock_toml_ops = MockTomlOperations::new();
         let mock_os_ops = MockOSOperations::new();
         let mock_shell = MockShellExecutor::new();
         let mock_prompt_ops = MockPromptOperations::new();
 
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
         mock_toml_ops
             .expect_parse()
             .returning(|_| Err(AppError::TomlParse(toml::de::Error::custom("Invalid TOML"))));
@@ -350,10 +631,11 @@ mod test {
             Arc::new(mock_os_ops),
             Arc::new(mock_shell),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
         );
 
         let result = load_service
-            .load(Path::new("/config.toml"), Path::new("/target"))
+            .load(Path::new("/config.toml"), Path::new("/target"), None)
             .await;
 
         assert!(matches!(result, Err(AppError::TomlParse(_))));
@@ -361,7 +643,8 @@ mod test {
 
     #[tokio::test]
     async fn test_load_with_provision_different_os() {
-        let mock_link_ops = MockLinkOperations::new();
+        let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
         let mut mock_path_ops = MockPathOperations::new();
         let mut mock_toml_ops = MockTomlOperations::new();
         let mut mock_os_ops = MockOSOperations::new();
@@ -377,6 +660,7 @@ mod test {
                 provision: Some(vec![crate::models::config::Provision {
                     mode: "linux".to_string(),
                     script: "echo 'Hello, World!'".to_string(),
+                    ..Default::default()
                 }]),
                 ..Default::default()
             })
@@ -393,10 +677,11 @@ mod test {
             Arc::new(mock_os_ops),
             Arc::new(mock_shell),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
         );
 
         let result = load_service
-            .load(Path::new("/config.toml"), Path::new("/target"))
+            .load(Path::new("/config.toml"), Path::new("/target"), None)
             .await;
 
         assert!(result.is_ok());
@@ -405,20 +690,34 @@ mod test {
     #[tokio::test]
     async fn test_load_with_links() {
         let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
         let mut mock_path_ops = MockPathOperations::new();
         let mut mock_toml_ops = MockTomlOperations::new();
-        let mock_os_ops = MockOSOperations::new();
+        let mut mock_os_ops = MockOSOperations::new();
         let mock_shell = MockShellExecutor::new();
         let mut mock_prompt_ops = MockPromptOperations::new();
 
         mock_path_ops
             .expect_parse_path()
             .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+
+        mock_os_ops
+            .expect_get_os()
+            .returning(|| Ok("linux".to_string()));
 
         mock_toml_ops.expect_parse().returning(|_| {
             Ok(Config {
                 link: Some(vec![crate::models::config::Link {
                     location: PathBuf::from("/source"),
+                    ..Default::default()
+                }]),
+                permission: Some(vec![Permission {
+                    path: ".ssh/*".to_string(),
+                    mode: "600".to_string(),
+                    owner: None,
                 }]),
                 ..Default::default()
             })
@@ -428,12 +727,357 @@ mod test {
             .expect_confirm_action()
             .returning(|_| Ok(true));
 
-        mock_link_ops.expect_link_recursively().returning(|_, _| {
-            Ok(vec![FileProcessResult::Linked(
-                PathBuf::from("/source/file"),
-                PathBuf::from("/target/file"),
+        mock_link_ops
+            .expect_link_recursively()
+            .returning(|_, _, _| {
+                Ok(vec![FileProcessResult::Linked(
+                    PathBuf::from("/source/file"),
+                    PathBuf::from("/target/file"),
+                )])
+            });
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|_, _, _| Ok(LinkPlan::default()));
+
+        // Permissions are applied in the run once the links are in place, which first records
+        // the modes they replace
+        let mut mock_permission_ops = MockPermissionOperations::new();
+        mock_permission_ops
+            .expect_check()
+            .times(1)
+            .returning(|target, _| {
+                Ok(vec![LinkStatus::WrongMode(
+                    target.join(".ssh/config"),
+                    0o600,
+                    0o644,
+                )])
+            });
+        mock_link_ops
+            .expect_record_permissions()
+            .withf(|path| path == Path::new("/target/.ssh/config"))
+            .times(1)
+            .returning(|_| Ok(()));
+        mock_permission_ops
+            .expect_apply()
+            .withf(|target, rules| target == Path::new("/target") && rules.len() == 1)
+            .times(1)
+            .returning(|target, _| {
+                Ok(vec![FileProcessResult::ModeChanged(
+                    target.join(".ssh/config"),
+                    0o600,
+                )])
+            });
+
+        let load_service = LoadServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(mock_os_ops),
+            Arc::new(mock_shell),
+            Arc::new(mock_prompt_ops),
+            Arc::new(mock_permission_ops),
+        );
+
+        let result = load_service
+            .load(Path::new("/config.toml"), Path::new("/target"), None)
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_load_rolls_back_when_permissions_fail() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_toml_ops = MockTomlOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_toml_ops.expect_parse().returning(|_| {
+            Ok(Config {
+                permission: Some(vec![Permission {
+                    path: ".ssh".to_string(),
+                    mode: "700".to_string(),
+                    owner: None,
+                }]),
+                ..Default::default()
+            })
+        });
+
+        mock_link_ops
+            .expect_begin_run()
+            .times(1)
+            .returning(|| Ok(()));
+        mock_link_ops
+            .expect_plan_prune_sources()
+            .returning(|_| Ok(LinkPlan::default()));
+        mock_link_ops
+            .expect_record_permissions()
+            .times(1)
+            .returning(|_| Ok(()));
+        mock_link_ops.expect_commit_run().times(0);
+        mock_link_ops
+            .expect_rollback_run()
+            .times(1)
+            .returning(|e| e);
+
+        let mut mock_permission_ops = MockPermissionOperations::new();
+        mock_permission_ops.expect_check().returning(|target, _| {
+            Ok(vec![LinkStatus::WrongMode(
+                target.join(".ssh"),
+                0o700,
+                0o755,
             )])
         });
+        mock_permission_ops
+            .expect_apply()
+            .returning(|_, _| Err(AppError::Permission("chmod failed".to_string())));
+
+        let load_service = LoadServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(MockOSOperations::new()),
+            Arc::new(MockShellExecutor::new()),
+            Arc::new(MockPromptOperations::new()),
+            Arc::new(mock_permission_ops),
+        );
+
+        let result = load_service
+            .load(Path::new("/config.toml"), Path::new("/target"), None)
+            .await;
+
+        assert!(matches!(result, Err(AppError::Permission(_))));
+    }
+
+    #[tokio::test]
+    async fn test_load_applies_only_the_profile() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_toml_ops = MockTomlOperations::new();
+        let mut mock_os_ops = MockOSOperations::new();
+        let mut mock_shell = MockShellExecutor::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+        mock_os_ops
+            .expect_get_os()
+            .returning(|| Ok("linux".to_string()));
+
+        mock_toml_ops.expect_parse().returning(|_| {
+            let link = |location: &str, profiles: &[&str]| crate::models::config::Link {
+                location: PathBuf::from(location),
+                profiles: profiles.iter().map(|profile| profile.to_string()).collect(),
+                ..Default::default()
+            };
+            Ok(Config {
+                link: Some(vec![
+                    link("/source", &[]),
+                    link("/base-source", &["base"]),
+                    link("/work-source", &["work"]),
+                    link("/ci-source", &["ci"]),
+                ]),
+                provision: Some(vec![crate::models::config::Provision {
+                    mode: "linux".to_string(),
+                    script: "echo 'Hello, CI!'".to_string(),
+                    profiles: vec!["ci".to_string()],
+                }]),
+                profile: BTreeMap::from([(
+                    "work".to_string(),
+                    crate::models::config::Profile {
+                        extends: vec!["base".to_string()],
+                        ..Default::default()
+                    },
+                )]),
+                ..Default::default()
+            })
+        });
+
+        mock_prompt_ops
+            .expect_confirm_action()
+            .returning(|_| Ok(true));
+        mock_link_ops
+            .expect_link_recursively()
+            .withf(|source, _, _| {
+                [
+                    Path::new("/source"),
+                    Path::new("/base-source"),
+                    Path::new("/work-source"),
+                ]
+                .contains(&source)
+            })
+            .times(3)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|_, _, _| Ok(LinkPlan::default()));
+        mock_shell.expect_execute().times(0);
+
+        let load_service = LoadServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(mock_os_ops),
+            Arc::new(mock_shell),
+            Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
+        );
+
+        let result = load_service
+            .load(
+                Path::new("/config.toml"),
+                Path::new("/target"),
+                Some("work"),
+            )
+            .await;
+        assert!(result.is_ok(), "{:?}", result);
+
+        let result = load_service
+            .load(
+                Path::new("/config.toml"),
+                Path::new("/target"),
+                Some("home"),
+            )
+            .await;
+        assert!(matches!(result, Err(AppError::Profile(_))));
+    }
+
+    #[tokio::test]
+    async fn test_load_prunes_sources_no_longer_configured() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        mock_link_ops.expect_begin_run().returning(|| Ok(()));
+        mock_link_ops.expect_commit_run().returning(|| Ok(()));
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_toml_ops = MockTomlOperations::new();
+        let mut mock_os_ops = MockOSOperations::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+        mock_os_ops
+            .expect_get_os()
+            .returning(|| Ok("linux".to_string()));
+        mock_toml_ops.expect_parse().returning(|_| {
+            Ok(Config {
+                link: Some(vec![crate::models::config::Link {
+                    location: PathBuf::from("/source"),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            })
+        });
+        mock_prompt_ops
+            .expect_confirm_action()
+            .returning(|_| Ok(true));
+        mock_link_ops
+            .expect_link_recursively()
+            .returning(|_, _, _| Ok(vec![]));
+        mock_link_ops
+            .expect_plan_prune()
+            .returning(|_, _, _| Ok(LinkPlan::default()));
+        mock_link_ops
+            .expect_plan_prune_sources()
+            .withf(|sources| sources == [PathBuf::from("/source")])
+            .times(1)
+            .returning(|_| {
+                Ok(LinkPlan {
+                    actions: vec![LinkAction::Unlink(
+                        PathBuf::from("/target/.removed"),
+                        PathBuf::from("/removed-source/.removed"),
+                    )],
+                    ..Default::default()
+                })
+            });
+        mock_link_ops
+            .expect_apply_plan()
+            .withf(|plan| plan.actions.len() == 1)
+            .times(1)
+            .returning(|_| Ok(vec![]));
+
+        let load_service = LoadServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(mock_os_ops),
+            Arc::new(MockShellExecutor::new()),
+            Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
+        );
+
+        let result = load_service
+            .load(Path::new("/config.toml"), Path::new("/target"), None)
+            .await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_load_links_sudo_entries_through_the_linker() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_toml_ops = MockTomlOperations::new();
+        let mut mock_os_ops = MockOSOperations::new();
+        let mut mock_shell = MockShellExecutor::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+
+        mock_os_ops
+            .expect_get_os()
+            .returning(|| Ok("linux".to_string()));
+
+        mock_toml_ops.expect_parse().returning(|_| {
+            Ok(Config {
+                link: Some(vec![crate::models::config::Link {
+                    location: PathBuf::from("/source"),
+                    target: Some(PathBuf::from("/nonexistent/etc/xdg")),
+                    include: vec!["autostart/**".to_string()],
+                    sudo: true,
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            })
+        });
+
+        mock_prompt_ops
+            .expect_confirm_action()
+            .withf(|message| message.contains("with sudo"))
+            .times(1)
+            .returning(|_| Ok(true));
+
+        // The entry goes through the linker like any other, which makes its changes with sudo
+        mock_link_ops
+            .expect_link_recursively()
+            .withf(|source, target, options| {
+                source == Path::new("/source")
+                    && target == Path::new("/nonexistent/etc/xdg")
+                    && options.include == vec!["autostart/**"]
+                    && options.sudo
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_link_ops
+            .expect_plan_prune()
+            .withf(|_, _, options| options.sudo)
+            .times(1)
+            .returning(|_, _, _| Ok(LinkPlan::default()));
+        mock_shell.expect_execute().times(0);
 
         let load_service = LoadServiceImpl::new(
             Arc::new(mock_link_ops),
@@ -442,10 +1086,99 @@ mod test {
             Arc::new(mock_os_ops),
             Arc::new(mock_shell),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
         );
 
         let result = load_service
-            .load(Path::new("/config.toml"), Path::new("/target"))
+            .load(Path::new("/config.toml"), Path::new("/home/alice"), None)
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_load_keeps_entry_targets_in_the_test_dir() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        expect_run(&mut mock_link_ops);
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_toml_ops = MockTomlOperations::new();
+        let mut mock_os_ops = MockOSOperations::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+
+        mock_os_ops
+            .expect_get_os()
+            .returning(|| Ok("linux".to_string()));
+
+        mock_toml_ops.expect_parse().returning(|_| {
+            Ok(Config {
+                link: Some(vec![
+                    crate::models::config::Link {
+                        location: PathBuf::from("/source/config"),
+                        target: Some(PathBuf::from("~/.config")),
+                        ..Default::default()
+                    },
+                    crate::models::config::Link {
+                        location: PathBuf::from("/source/xdg"),
+                        target: Some(PathBuf::from("/etc/xdg")),
+                        sudo: true,
+                        ..Default::default()
+                    },
+                ]),
+                ..Default::default()
+            })
+        });
+
+        mock_prompt_ops
+            .expect_confirm_action()
+            .withf(|message| !message.contains("with sudo"))
+            .times(2)
+            .returning(|_| Ok(true));
+
+        mock_link_ops
+            .expect_link_recursively()
+            .withf(|source, target, _| {
+                source == Path::new("/source/config")
+                    && target == Path::new("/home/alice/.test/.config")
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_link_ops
+            .expect_link_recursively()
+            .withf(|source, target, options| {
+                source == Path::new("/source/xdg")
+                    && target == Path::new("/home/alice/.test/etc/xdg")
+                    && !options.sudo
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_link_ops
+            .expect_plan_prune()
+            .times(2)
+            .returning(|_, _, _| Ok(LinkPlan::default()));
+
+        let load_service = LoadServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(mock_os_ops),
+            Arc::new(MockShellExecutor::new()),
+            Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
+        );
+
+        let result = load_service
+            .load(
+                Path::new("/config.toml"),
+                Path::new("/home/alice/.test"),
+                None,
+            )
             .await;
 
         assert!(result.is_ok());
@@ -463,6 +1196,9 @@ mod test {
         mock_path_ops
             .expect_parse_path()
             .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/user")));
 
         mock_prompt_ops
             .expect_confirm_action()
@@ -475,10 +1211,15 @@ mod test {
             Arc::new(mock_os_ops),
             Arc::new(mock_shell),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
         );
 
         let result = load_service
-            .link_dotfiles(Path::new("/source"), Path::new("/target"))
+            .link_dotfiles(
+                Path::new("/source"),
+                Path::new("/target"),
+                &LinkOptions::default(),
+            )
             .await;
 
         assert!(result.is_ok());
@@ -507,6 +1248,7 @@ mod test {
             Arc::new(mock_os_ops),
             Arc::new(mock_shell),
             Arc::new(mock_prompt_ops),
+            Arc::new(MockPermissionOperations::new()),
         );
 
         let result = load_service.run_bash_script("echo 'test'").await;
@@ -514,4 +1256,196 @@ mod test {
         assert!(result.is_err());
         assert!(matches!(result, Err(AppError::ShellExecution(_))));
     }
+
+    #[tokio::test]
+    async fn test_plan_does_not_execute() {
+        let mut mock_link_ops = MockLinkOperations::new();
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_toml_ops = MockTomlOperations::new();
+        let mut mock_os_ops = MockOSOperations::new();
+        let mut mock_shell = MockShellExecutor::new();
+        let mut mock_prompt_ops = MockPromptOperations::new();
+
+        mock_path_ops
+            .expect_parse_path()
+            .returning(|path| Ok(path.to_path_buf()));
+        mock_path_ops
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+
+        mock_toml_ops.expect_parse().returning(|_| {
+            Ok(Config {
+                link: Some(vec![crate::models::config::Link {
+                    location: PathBuf::from("/source"),
+                    ..Default::default()
+                }]),
+                provision: Some(vec![
+                    crate::models::config::Provision {
+                        mode: "macos".to_string(),
+                        script: "echo 'Hello, macOS!'".to_string(),
+                        ..Default::default()
+                    },
+                    crate::models::config::Provision {
+                        mode: "linux".to_string(),
+                        script: "echo 'Hello, Linux!'".to_string(),
+                        ..Default::default()
+                    },
+                ]),
+                vars: BTreeMap::from([
+                    ("email".to_string(), "alice@example.com".to_string()),
+                    ("home".to_string(), "/Users/alice".to_string()),
+                ]),
+                permission: Some(vec![Permission {
+                    path: ".ssh".to_string(),
+                    mode: "700".to_string(),
+                    owner: None,
+                }]),
+                ..Default::default()
+            })
+        });
+
+        mock_os_ops
+            .expect_get_os()
+            .returning(|| Ok("macos".to_string()));
+
+        // Config values win over the built-in variables of the same name
+        mock_link_ops
+            .expect_plan_links()
+            .withf(|_, _, options| {
+                options.vars["os"] == "macos"
+                    && options.vars["email"] == "alice@example.com"
+                    && options.vars["home"] == "/Users/alice"
+            })
+            .times(1)
+            .returning(|source, target, _| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![],
+                    ..Default::default()
+                })
+            });
+        mock_link_ops
+            .expect_plan_prune()
+            .times(1)
+            .returning(|_, target, _| {
+                Ok(LinkPlan {
+                    actions: vec![LinkAction::Remove(target.join(".gitconfig"))],
+                    ..Default::default()
+                })
+            });
+        mock_link_ops
+            .expect_plan_prune_sources()
+            .withf(|sources| sources == [PathBuf::from("/source")])
+            .times(1)
+            .returning(|_| {
+                Ok(LinkPlan {
+                    actions: vec![LinkAction::Remove(PathBuf::from("/target/.vpn"))],
+                    ..Default::default()
+                })
+            });
+        mock_link_ops.expect_link_recursively().times(0);
+        mock_link_ops.expect_apply_plan().times(0);
+        mock_shell.expect_execute().times(0);
+        mock_prompt_ops.expect_confirm_action().times(0);
+
+        let mut mock_permission_ops = MockPermissionOperations::new();
+        mock_permission_ops
+            .expect_check()
+            .times(1)
+            .returning(|target, _| {
+                Ok(vec![LinkStatus::WrongMode(
+                    target.join(".ssh"),
+                    0o700,
+                    0o755,
+                )])
+            });
+        mock_permission_ops.expect_apply().times(0);
+
+        let load_service = LoadServiceImpl::new(
+            Arc::new(mock_link_ops),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(mock_os_ops),
+            Arc::new(mock_shell),
+            Arc::new(mock_prompt_ops),
+            Arc::new(mock_permission_ops),
+        );
+
+        let plan = load_service
+            .plan(Path::new("/config.toml"), Path::new("/target"), None)
+            .await
+            .unwrap();
+
+        assert_eq!(plan.links.len(), 1);
+        assert_eq!(plan.links[0].source, PathBuf::from("/source"));
+        assert_eq!(
+            plan.links[0].actions,
+            vec![LinkAction::Remove(PathBuf::from("/target/.gitconfig"))]
+        );
+        assert_eq!(plan.stale.actions.len(), 1);
+        assert_eq!(plan.permissions.len(), 1);
+        assert_eq!(plan.provisions.len(), 1);
+        assert_eq!(plan.provisions[0].mode, "macos");
+    }
+
+    #[tokio::test]
+    async fn test_check_permissions_selects_the_config() {
+        let mut mock_path_ops = MockPathOperations::new();
+        let mut mock_toml_ops = MockTomlOperations::new();
+        let mut mock_permission_ops = MockPermissionOperations::new();
+
+        mock_path_ops.expect_parse_path().returning(|path| {
+            Ok(match path.strip_prefix("~") {
+                Ok(relative) => Path::new("/home/alice").join(relative),
+                Err(_) => path.to_path_buf(),
+            })
+        });
+        mock_toml_ops
+            .expect_parse()
+            .withf(|path| path == Path::new("/home/alice/.config/widots/config.toml"))
+            .returning(|_| {
+                Ok(Config {
+                    permission: Some(vec![Permission {
+                        path: ".ssh".to_string(),
+                        mode: "700".to_string(),
+                        owner: None,
+                    }]),
+                    profile: BTreeMap::from([(
+                        "work".to_string(),
+                        crate::models::config::Profile::default(),
+                    )]),
+                    ..Default::default()
+                })
+            });
+        mock_permission_ops
+            .expect_check()
+            .times(1)
+            .returning(|_, rules| {
+                assert_eq!(rules.len(), 1);
+                Ok(vec![])
+            });
+
+        let load_service = LoadServiceImpl::new(
+            Arc::new(MockLinkOperations::new()),
+            Arc::new(mock_path_ops),
+            Arc::new(mock_toml_ops),
+            Arc::new(MockOSOperations::new()),
+            Arc::new(MockShellExecutor::new()),
+            Arc::new(MockPromptOperations::new()),
+            Arc::new(mock_permission_ops),
+        );
+
+        let config_path = Path::new("~/.config/widots/config.toml");
+        let result = load_service
+            .check_permissions(config_path, Path::new("/target"), Some("work"))
+            .await;
+        assert!(result.is_ok(), "{:?}", result);
+
+        // A profile the config does not know is refused, as `load` refuses it
+        let result = load_service
+            .check_permissions(config_path, Path::new("/target"), Some("home"))
+            .await;
+        assert!(matches!(result, Err(AppError::Profile(_))));
+    }
 }
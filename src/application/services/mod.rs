@@ -3,4 +3,5 @@ pub mod deploy_service;
 pub mod fish_service;
 pub mod link_service;
 pub mod load_service;
+pub mod sync_service;
 pub mod vscode_service;
@@ -0,0 +1,264 @@
+use crate::domain::path::PathOperations;
+use crate::domain::shell::ShellExecutor;
+use crate::error::AppError;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[async_trait]
+pub trait SyncService: Send + Sync {
+    async fn repo_of(&self, config_path: &Path) -> Result<PathBuf, AppError>;
+    async fn pull(&self, repo: &Path) -> Result<(), AppError>;
+    async fn changes(&self, repo: &Path) -> Result<Vec<String>, AppError>;
+    async fn commit(&self, repo: &Path, changes: &[String]) -> Result<String, AppError>;
+    async fn push(&self, repo: &Path) -> Result<(), AppError>;
+}
+
+pub struct SyncServiceImpl {
+    shell_executor: Arc<dyn ShellExecutor>,
+    path_operations: Arc<dyn PathOperations>,
+}
+
+impl SyncServiceImpl {
+    pub fn new(
+        shell_executor: Arc<dyn ShellExecutor>,
+        path_operations: Arc<dyn PathOperations>,
+    ) -> Self {
+        Self {
+            shell_executor,
+            path_operations,
+        }
+    }
+
+    async fn git(&self, repo: &Path, args: &[&str]) -> Result<String, AppError> {
+        let repo = self.path_operations.parse_path(repo).await?;
+        let repo = repo.display().to_string();
+        let mut git_args = vec!["-C", repo.as_str()];
+        git_args.extend_from_slice(args);
+        self.shell_executor.execute("git", &git_args).await
+    }
+
+    async fn conflicts(&self, repo: &Path) -> Result<Vec<String>, AppError> {
+        let unmerged = self
+            .git(repo, &["diff", "--name-only", "--diff-filter=U"])
+            .await?;
+        Ok(unmerged.lines().map(String::from).collect())
+    }
+}
+
+/// Generates a commit message from `git status --porcelain` lines: a subject naming the change,
+/// and a body listing every path.
+fn commit_message(changes: &[String]) -> String {
+    let paths: Vec<&str> = changes
+        .iter()
+        .map(|change| change.get(3..).unwrap_or(change))
+        .collect();
+    let subject = match paths.as_slice() {
+        [path] => format!("Update {}", path),
+        _ => format!("Update {} dotfiles", paths.len()),
+    };
+    format!("{}\n\n{}", subject, paths.join("\n"))
+}
+
+#[async_trait]
+impl SyncService for SyncServiceImpl {
+    /// Returns the git repository holding the config file, following the links to it, as when
+    /// the config is itself linked into `~/.config` from the dotfiles.
+    async fn repo_of(&self, config_path: &Path) -> Result<PathBuf, AppError> {
+        let config_path = self.path_operations.expand_tilde(config_path).await?;
+        let config_path = tokio::fs::canonicalize(&config_path).await?;
+        let dir = config_path.parent().unwrap_or(Path::new("/"));
+        match self.git(dir, &["rev-parse", "--show-toplevel"]).await {
+            Ok(toplevel) => Ok(PathBuf::from(toplevel.trim_end())),
+            Err(_) => Err(AppError::SyncRepoNotFound(config_path)),
+        }
+    }
+
+    /// Pulls with rebase, stashing local changes around it. On conflicts the rebase is aborted,
+    /// so the repository is left as it was before the pull.
+    async fn pull(&self, repo: &Path) -> Result<(), AppError> {
+        let pulled = self.git(repo, &["pull", "--rebase", "--autostash"]).await;
+        let conflicts = self.conflicts(repo).await.unwrap_or_default();
+        if conflicts.is_empty() {
+            return pulled.map(|_| ());
+        }
+
+        // When only the stashed local changes conflict, the pull itself succeeded and git keeps
+        // the stash for them to be resolved by hand
+        if pulled.is_err() {
+            self.git(repo, &["rebase", "--abort"]).await?;
+        }
+        Err(AppError::SyncConflict(repo.to_path_buf(), conflicts))
+    }
+
+    async fn changes(&self, repo: &Path) -> Result<Vec<String>, AppError> {
+        let status = self.git(repo, &["status", "--porcelain"]).await?;
+        Ok(status.lines().map(String::from).collect())
+    }
+
+    async fn commit(&self, repo: &Path, changes: &[String]) -> Result<String, AppError> {
+        let message = commit_message(changes);
+        self.git(repo, &["add", "--all"]).await?;
+        self.git(repo, &["commit", "--message", &message]).await?;
+        Ok(message)
+    }
+
+    async fn push(&self, repo: &Path) -> Result<(), AppError> {
+        self.git(repo, &["push"]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::path::PathExpander;
+    use crate::infrastructure::shell::executor::SystemShellExecutor;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use tempfile::TempDir;
+    use tokio::fs;
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .expect("git should run");
+        assert!(
+            output.status.success(),
+            "git {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Creates a bare repository with one commit, and two clones of it.
+    fn clones(root: &Path) -> (PathBuf, PathBuf) {
+        let remote = root.join("remote.git");
+        let (a, b) = (root.join("a"), root.join("b"));
+        git(root, &["init", "--bare", remote.to_str().unwrap()]);
+        for clone in [&a, &b] {
+            git(
+                root,
+                &["clone", remote.to_str().unwrap(), clone.to_str().unwrap()],
+            );
+            git(clone, &["config", "user.name", "widots"]);
+            git(clone, &["config", "user.email", "widots@example.com"]);
+            git(clone, &["config", "commit.gpgsign", "false"]);
+            if clone == &a {
+                std::fs::write(a.join(".vimrc"), "set number\n").unwrap();
+                git(&a, &["add", "--all"]);
+                git(&a, &["commit", "--message", "Add .vimrc"]);
+                git(&a, &["push", "--set-upstream", "origin", "HEAD"]);
+            }
+        }
+        git(&b, &["pull"]);
+        (a, b)
+    }
+
+    fn sync_service() -> SyncServiceImpl {
+        SyncServiceImpl::new(
+            Arc::new(SystemShellExecutor::new()),
+            Arc::new(PathExpander::new()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_repo_of() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let (a, _) = clones(temp_dir.path());
+        fs::create_dir_all(a.join("widots")).await?;
+        fs::write(a.join("widots/config.toml"), "").await?;
+
+        // A config linked out of the repository still belongs to it
+        let config_dir = temp_dir.path().join(".config");
+        fs::create_dir_all(&config_dir).await?;
+        fs::symlink(a.join("widots"), config_dir.join("widots")).await?;
+        let repo = sync_service()
+            .repo_of(&config_dir.join("widots/config.toml"))
+            .await?;
+        assert_eq!(repo, fs::canonicalize(&a).await?);
+
+        fs::write(config_dir.join("config.toml"), "").await?;
+        let result = sync_service()
+            .repo_of(&config_dir.join("config.toml"))
+            .await;
+        assert!(matches!(result, Err(AppError::SyncRepoNotFound(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_message() {
+        assert_eq!(
+            commit_message(&[" M .vimrc".to_string()]),
+            "Update .vimrc\n\n.vimrc"
+        );
+        assert_eq!(
+            commit_message(&[" M .vimrc".to_string(), "?? .bashrc".to_string()]),
+            "Update 2 dotfiles\n\n.vimrc\n.bashrc"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_round_trip() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let (a, b) = clones(temp_dir.path());
+        fs::write(a.join(".vimrc"), "set relativenumber\n").await?;
+        git(&a, &["commit", "--all", "--message", "Change .vimrc"]);
+        git(&a, &["push"]);
+
+        // Local changes survive the pull and are committed and pushed
+        fs::write(b.join(".bashrc"), "alias ll='ls -l'\n").await?;
+        let service = sync_service();
+        service.pull(&b).await?;
+        assert_eq!(
+            fs::read_to_string(b.join(".vimrc")).await?,
+            "set relativenumber\n"
+        );
+
+        let changes = service.changes(&b).await?;
+        assert_eq!(changes, vec!["?? .bashrc".to_string()]);
+        let message = service.commit(&b, &changes).await?;
+        assert!(message.starts_with("Update .bashrc"));
+        assert!(service.changes(&b).await?.is_empty());
+        service.push(&b).await?;
+
+        git(&a, &["pull"]);
+        assert!(a.join(".bashrc").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pull_stops_on_conflicts() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let (a, b) = clones(temp_dir.path());
+        fs::write(a.join(".vimrc"), "set relativenumber\n").await?;
+        git(&a, &["commit", "--all", "--message", "Change .vimrc in a"]);
+        git(&a, &["push"]);
+        fs::write(b.join(".vimrc"), "set nonumber\n").await?;
+        git(&b, &["commit", "--all", "--message", "Change .vimrc in b"]);
+        let head = git(&b, &["rev-parse", "HEAD"]);
+
+        let result = sync_service().pull(&b).await;
+
+        assert!(
+            matches!(&result, Err(AppError::SyncConflict(_, files)) if files == &[".vimrc"]),
+            "{:?}",
+            result
+        );
+        // The rebase was aborted, leaving the local commit as it was
+        assert_eq!(git(&b, &["rev-parse", "HEAD"]), head);
+        assert!(git(&b, &["status", "--porcelain"]).is_empty());
+        assert_eq!(
+            fs::read_to_string(b.join(".vimrc")).await?,
+            "set nonumber\n"
+        );
+
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
+use crate::application::config::select_config_if_any;
 use crate::constants::{RESOURCES_DIR, VSCODE_EXTENSIONS_FILENAME};
 use crate::domain::os::OSOperations;
+use crate::domain::path::PathOperations;
 use crate::domain::shell::ShellExecutor;
 use crate::error::AppError;
 use crate::infrastructure::fs::FileSystemOperations;
+use crate::utils::toml::TomlOperations;
 use async_trait::async_trait;
 use std::path::Path;
 use std::sync::Arc;
@@ -10,7 +13,11 @@ use std::sync::Arc;
 #[async_trait]
 pub trait VSCodeService: Send + Sync {
     async fn export_extensions(&self) -> Result<(), AppError>;
-    async fn import_extensions(&self) -> Result<(), AppError>;
+    async fn import_extensions<'a>(
+        &self,
+        config_path: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<(), AppError>;
     async fn ensure_code_command(&self) -> Result<(), AppError>;
 }
 
@@ -18,6 +25,8 @@ pub struct VSCodeServiceImpl {
     shell_executor: Arc<dyn ShellExecutor>,
     fs_operations: Arc<dyn FileSystemOperations>,
     os_detector: Arc<dyn OSOperations>,
+    path_operations: Arc<dyn PathOperations>,
+    toml_parser: Arc<dyn TomlOperations>,
 }
 
 impl VSCodeServiceImpl {
@@ -25,11 +34,15 @@ impl VSCodeServiceImpl {
         shell_executor: Arc<dyn ShellExecutor>,
         fs_operations: Arc<dyn FileSystemOperations>,
         os_detector: Arc<dyn OSOperations>,
+        path_operations: Arc<dyn PathOperations>,
+        toml_parser: Arc<dyn TomlOperations>,
     ) -> Self {
         Self {
             shell_executor,
             fs_operations,
             os_detector,
+            path_operations,
+            toml_parser,
         }
     }
 }
@@ -54,9 +67,23 @@ impl VSCodeService for VSCodeServiceImpl {
         Ok(())
     }
 
-    async fn import_extensions(&self) -> Result<(), AppError> {
+    /// Installs the exported extensions, then those the config lists for `profile`.
+    async fn import_extensions<'a>(
+        &self,
+        config_path: &'a Path,
+        profile: Option<&'a str>,
+    ) -> Result<(), AppError> {
+        let config = select_config_if_any(
+            self.path_operations.as_ref(),
+            self.toml_parser.as_ref(),
+            config_path,
+            profile,
+        )
+        .await?;
+
         let import_path = Path::new(RESOURCES_DIR).join(VSCODE_EXTENSIONS_FILENAME);
-        let extensions = self.fs_operations.read_lines(&import_path).await?;
+        let mut extensions = self.fs_operations.read_lines(&import_path).await?;
+        extensions.extend(config.packages(|package| &package.vscode));
         for extension in extensions {
             self.shell_executor
                 .execute("code", &["--install-extension", extension.as_str()])
@@ -90,11 +117,15 @@ mod test {
     use crate::domain::shell::ShellExecutor;
     use crate::error::AppError;
     use crate::infrastructure::fs::FileSystemOperations;
+    use crate::infrastructure::path::PathExpander;
+    use crate::utils::toml::TomlParser;
     use async_trait::async_trait;
     use mockall::mock;
     use std::path::Path;
     use std::sync::Arc;
 
+    const NO_CONFIG: &str = "/nonexistent/config.toml";
+
     mock! {
         ShellExecutor {}
         #[async_trait]
@@ -135,8 +166,13 @@ mod test {
 
         mock_fs.expect_write_lines().returning(|_, _| Ok(()));
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = vscode_service.export_extensions().await;
         assert!(result.is_ok());
@@ -156,10 +192,17 @@ mod test {
             .expect_execute()
             .returning(|_, _| Ok("Extension installed successfully".to_string()));
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
-
-        let result = vscode_service.import_extensions().await;
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
+
+        let result = vscode_service
+            .import_extensions(Path::new(NO_CONFIG), None)
+            .await;
         assert!(result.is_ok());
     }
 
@@ -179,8 +222,13 @@ mod test {
             Ok(())
         });
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = vscode_service.export_extensions().await;
         assert!(result.is_ok());
@@ -197,8 +245,13 @@ mod test {
             .withf(|cmd: &str, args: &[&str]| cmd == "code" && args == ["--list-extensions"])
             .returning(|_, _| Err(AppError::ShellExecution("Command failed".to_string())));
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = vscode_service.export_extensions().await;
         assert!(result.is_err());
@@ -213,10 +266,17 @@ mod test {
 
         mock_fs.expect_read_lines().returning(|_| Ok(vec![]));
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
-
-        let result = vscode_service.import_extensions().await;
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
+
+        let result = vscode_service
+            .import_extensions(Path::new(NO_CONFIG), None)
+            .await;
         assert!(result.is_ok());
     }
 
@@ -236,10 +296,17 @@ mod test {
             ))
         });
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
-
-        let result = vscode_service.import_extensions().await;
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
+
+        let result = vscode_service
+            .import_extensions(Path::new(NO_CONFIG), None)
+            .await;
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), AppError::ShellExecution(_)));
     }
@@ -255,8 +322,13 @@ mod test {
             .withf(|cmd: &str, args: &[&str]| cmd == "which" && args == ["code"])
             .returning(|_, _| Ok("/usr/local/bin/code".to_string()));
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = vscode_service.ensure_code_command().await;
         assert!(result.is_ok());
@@ -290,8 +362,13 @@ mod test {
                 })
                 .returning(|_, _| Ok("Symlink created".to_string()));
         }
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = vscode_service.ensure_code_command().await;
         if Path::new("/Applications/Visual Studio Code.app").exists() {
@@ -320,8 +397,13 @@ mod test {
             .expect_get_os()
             .returning(|| Ok("linux".to_string()));
 
-        let vscode_service =
-            VSCodeServiceImpl::new(Arc::new(mock_shell), Arc::new(mock_fs), Arc::new(mock_os));
+        let vscode_service = VSCodeServiceImpl::new(
+            Arc::new(mock_shell),
+            Arc::new(mock_fs),
+            Arc::new(mock_os),
+            Arc::new(PathExpander::new()),
+            Arc::new(TomlParser::new()),
+        );
 
         let result = vscode_service.ensure_code_command().await;
         assert!(result.is_err());
@@ -1,31 +1,38 @@
-use std::collections::HashSet;
-use std::sync::LazyLock;
-
-pub static LINK_IGNORED_FILES: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
-    let mut s = HashSet::new();
-    s.insert(".DS_Store");
-    s.insert(".gitignore");
-    s
-});
-
-pub static LINK_IGNORED_PREFIXES: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
-    let mut s = HashSet::new();
-    s.insert("_");
-    s
-});
-
-pub static LINK_IGNORED_ANCESTORS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
-    let mut s = HashSet::new();
-    s.insert(".git");
-    s.insert("node_modules");
-    s
-});
+pub const IGNORE_FILENAME: &str = ".widotsignore";
+pub const GITIGNORE_FILENAME: &str = ".gitignore";
+pub const FOLD_MARKER_FILENAME: &str = ".widotsfold";
+pub const TEMPLATE_EXTENSION: &str = "tmpl";
+pub const SECRET_EXTENSION: &str = "age";
+pub const SECRET_MODE: u32 = 0o600;
+pub const PRIVATE_DIRS: &[&str] = &[".ssh", ".gnupg"];
+pub const ALTERNATE_SEPARATOR: &str = "##";
+pub const MATERIALIZE_STAGING_SUFFIX: &str = ".widots-materialize";
+pub const JOURNAL_STASH_SUFFIX: &str = ".widots-stash";
+pub const LINK_CONCURRENCY: usize = 64;
+pub const WATCH_DEBOUNCE_MS: u64 = 500;
+pub const DEFAULT_IGNORE_RULES: &[&str] = &[
+    ".DS_Store",
+    ".gitignore",
+    ".widotsignore",
+    ".widotsfold",
+    "_*",
+    ".git",
+    "node_modules",
+    "**/git/ignore",
+    "**/git/config",
+];
 
 pub const APP_NAME: &str = "widots";
 pub const DEFAULT_CONFIG_TOML: &str = "~/.config/widots/config.toml";
 
 pub const TEST_HOME_DIR: &str = ".widots-test";
 
+pub const STATE_FILENAME: &str = "state.toml";
+pub const IDENTITY_FILENAME: &str = "identity.txt";
+
+pub const BACKUPS_DIR: &str = "backups";
+pub const BACKUP_RUN_ID_FORMAT: &str = "%Y%m%d-%H%M%S";
+
 pub const BREW_FORMULA_FILENAME: &str = "brew_formulas.txt";
 pub const BREW_CASK_FORMULA_FILENAME: &str = "brew_casks.txt";
 
@@ -0,0 +1,12 @@
+use crate::error::AppError;
+use crate::models::link::FileProcessResult;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+#[async_trait]
+pub trait BackupOperations: Send + Sync {
+    fn new_run_id(&self) -> String;
+    async fn reserve(&self, run_id: &str, path: &Path) -> Result<PathBuf, AppError>;
+    async fn restore(&self, run_id: &str) -> Result<Vec<FileProcessResult>, AppError>;
+    async fn list_runs(&self) -> Result<Vec<String>, AppError>;
+}
@@ -1,7 +1,10 @@
 use crate::error::AppError;
-use crate::models::link::FileProcessResult;
+use crate::models::diff::FileDiff;
+use crate::models::link::{FileProcessResult, LinkOptions};
+use crate::models::plan::LinkPlan;
+use crate::models::status::LinkStatus;
 use async_trait::async_trait;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[async_trait]
 pub trait LinkOperations: Send + Sync {
@@ -9,12 +12,73 @@ pub trait LinkOperations: Send + Sync {
         &self,
         source: &Path,
         target: &Path,
+        options: &LinkOptions,
     ) -> Result<Vec<FileProcessResult>, AppError>;
 
-    async fn materialize_symlinks_recursively(
+    async fn plan_links(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<LinkPlan, AppError>;
+
+    async fn plan_unlinks(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+
+    async fn plan_prune(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<LinkPlan, AppError>;
+
+    /// Plans the removal of what `load` linked from sources that are in none of `sources`.
+    async fn plan_prune_sources(&self, sources: &[PathBuf]) -> Result<LinkPlan, AppError>;
+
+    async fn check_status(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<Vec<LinkStatus>, AppError>;
+
+    async fn diff_files(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &LinkOptions,
+    ) -> Result<Vec<FileDiff>, AppError>;
+
+    async fn add_to_source(
         &self,
+        source: &Path,
         target: &Path,
+        path: &Path,
+        options: &LinkOptions,
     ) -> Result<Vec<FileProcessResult>, AppError>;
 
-    fn should_ignore(&self, path: &Path) -> bool;
+    async fn encrypt_into_source(
+        &self,
+        source: &Path,
+        target: &Path,
+        path: &Path,
+        options: &LinkOptions,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+
+    async fn apply_plan(&self, plan: &LinkPlan) -> Result<Vec<FileProcessResult>, AppError>;
+
+    async fn begin_run(&self) -> Result<(), AppError>;
+
+    async fn commit_run(&self) -> Result<(), AppError>;
+
+    async fn rollback_run(&self, error: AppError) -> AppError;
+
+    /// Records the mode and owner of `path` in the run before they are changed, so that a
+    /// rollback restores them.
+    async fn record_permissions(&self, path: &Path) -> Result<(), AppError>;
+
+    async fn materialize_symlinks_recursively(
+        &self,
+        target: &Path,
+        source: Option<PathBuf>,
+    ) -> Result<Vec<FileProcessResult>, AppError>;
 }
@@ -1,5 +1,10 @@
+pub mod backup;
 pub mod link;
 pub mod os;
 pub mod path;
+pub mod permission;
 pub mod prompt;
+pub mod secret;
 pub mod shell;
+pub mod state;
+pub mod watch;
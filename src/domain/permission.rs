@@ -0,0 +1,17 @@
+use crate::error::AppError;
+use crate::models::config::Permission;
+use crate::models::link::FileProcessResult;
+use crate::models::status::LinkStatus;
+use async_trait::async_trait;
+use std::path::Path;
+
+#[async_trait]
+pub trait PermissionOperations: Send + Sync {
+    async fn check(&self, target: &Path, rules: &[Permission])
+        -> Result<Vec<LinkStatus>, AppError>;
+    async fn apply(
+        &self,
+        target: &Path,
+        rules: &[Permission],
+    ) -> Result<Vec<FileProcessResult>, AppError>;
+}
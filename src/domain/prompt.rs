@@ -4,4 +4,5 @@ use async_trait::async_trait;
 #[async_trait]
 pub trait PromptOperations: Send + Sync {
     async fn confirm_action(&self, message: &str) -> Result<bool, AppError>;
+    async fn select(&self, message: &str, choices: &[String]) -> Result<usize, AppError>;
 }
@@ -0,0 +1,9 @@
+use crate::error::AppError;
+use async_trait::async_trait;
+use std::path::Path;
+
+#[async_trait]
+pub trait SecretOperations: Send + Sync {
+    async fn decrypt(&self, path: &Path) -> Result<Vec<u8>, AppError>;
+    async fn encrypt(&self, path: &Path) -> Result<Vec<u8>, AppError>;
+}
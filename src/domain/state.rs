@@ -0,0 +1,9 @@
+use crate::error::AppError;
+use crate::models::state::LinkState;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait StateOperations: Send + Sync {
+    async fn load(&self) -> Result<LinkState, AppError>;
+    async fn save(&self, state: &LinkState) -> Result<(), AppError>;
+}
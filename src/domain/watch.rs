@@ -0,0 +1,14 @@
+use crate::error::AppError;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+#[async_trait]
+pub trait WatchOperations: Send + Sync {
+    async fn watch(
+        &self,
+        path: &Path,
+        debounce: Duration,
+    ) -> Result<mpsc::Receiver<Vec<PathBuf>>, AppError>;
+}
@@ -21,9 +21,6 @@ pub enum AppError {
     #[error("File not found: {0}")]
     FileNotFound(PathBuf),
 
-    #[error("Invalid file name: {0}")]
-    InvalidFilename(String),
-
     #[error("Directory not found")]
     DirectoryNotFound,
 
@@ -38,4 +35,77 @@ pub enum AppError {
 
     #[error("Code command not installed")]
     CodeCommandNotInstalled,
+
+    #[error("Backup not found: {0}")]
+    BackupNotFound(String),
+
+    #[error("Invalid ignore rule: {0}")]
+    IgnoreRule(String),
+
+    #[error("Cannot add {0}: {1}")]
+    AddRejected(PathBuf, String),
+
+    #[error("Cannot render {0}: {1}")]
+    Template(PathBuf, String),
+
+    #[error("State file error: {0}")]
+    State(String),
+
+    #[error("Found {0} dotfiles that are not linked as expected")]
+    Drift(usize),
+
+    #[error("Could not materialize {0} links")]
+    Materialize(usize),
+
+    #[error("Watch error: {0}")]
+    Watch(String),
+
+    #[error("Cannot decrypt {0}: {1}")]
+    Decrypt(PathBuf, String),
+
+    #[error("Cannot encrypt {0}: {1}")]
+    Encrypt(PathBuf, String),
+
+    #[error("Invalid permission rule: {0}")]
+    Permission(String),
+
+    #[error("Interrupted")]
+    Interrupted,
+
+    #[error("{0} ({1})")]
+    RolledBack(Box<AppError>, String),
+
+    #[error(
+        "Pulling into {} conflicts in {}, so nothing was loaded, committed or pushed",
+        .0.display(),
+        .1.join(", ")
+    )]
+    SyncConflict(PathBuf, Vec<String>),
+
+    #[error(
+        "{} is not in a git repository, pass the dotfiles repository with --repo",
+        .0.display()
+    )]
+    SyncRepoNotFound(PathBuf),
+
+    #[error("Refusing to link {0} into {1}, which is inside it")]
+    TargetInsideSource(PathBuf, PathBuf),
+
+    #[error("Refusing to link from {0}, since it would take over every file in it")]
+    UnsafeSource(PathBuf),
+
+    #[error("Cannot pass {} to sudo, since it is not valid UTF-8", .0.display())]
+    NonUtf8Path(PathBuf),
+
+    #[error("Symlink loop at {0}")]
+    SymlinkLoop(PathBuf),
+
+    #[error("Refusing to replace the directory {0} without consent")]
+    DirectoryReplaceRefused(PathBuf),
+
+    #[error("Invalid profile: {0}")]
+    Profile(String),
+
+    #[error("--dry-run is only supported by link, unlink, prune and load")]
+    DryRunUnsupported,
 }
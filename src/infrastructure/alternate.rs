@@ -0,0 +1,201 @@
+use crate::constants::ALTERNATE_SEPARATOR;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
+
+/// One condition in an alternate's name, such as `os.linux` in `gitconfig##os.linux`.
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Default,
+    Os(String),
+    User(String),
+    Hostname(String),
+}
+
+impl Condition {
+    fn parse(condition: &str) -> Result<Self, String> {
+        if condition == "default" {
+            return Ok(Condition::Default);
+        }
+
+        let (key, value) = condition
+            .split_once('.')
+            .filter(|(_, value)| !value.is_empty())
+            .ok_or_else(|| format!("invalid condition `{}`", condition))?;
+        match key {
+            "os" | "o" => Ok(Condition::Os(value.to_string())),
+            "user" | "u" => Ok(Condition::User(value.to_string())),
+            "hostname" | "h" => Ok(Condition::Hostname(value.to_string())),
+            _ => Err(format!("unknown condition `{}`", condition)),
+        }
+    }
+
+    /// More specific conditions weigh more, so a hostname match beats an OS match.
+    fn weight(&self) -> u32 {
+        match self {
+            Condition::Default => 1,
+            Condition::Os(_) => 2,
+            Condition::User(_) => 4,
+            Condition::Hostname(_) => 8,
+        }
+    }
+
+    fn holds(&self, vars: &BTreeMap<String, String>) -> bool {
+        let (key, expected) = match self {
+            Condition::Default => return true,
+            Condition::Os(value) => ("os", value),
+            Condition::User(value) => ("user", value),
+            Condition::Hostname(value) => ("hostname", value),
+        };
+        vars.get(key) == Some(expected)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Condition::Default => "default".to_string(),
+            Condition::Os(value) => format!("os.{}", value),
+            Condition::User(value) => format!("user.{}", value),
+            Condition::Hostname(value) => format!("hostname.{}", value),
+        }
+    }
+}
+
+/// Returns the name an entry is linked under: `gitconfig` for `gitconfig##os.linux`. Names that
+/// are not valid UTF-8 cannot carry conditions and are their own base name.
+pub fn base_name(name: &OsStr) -> &OsStr {
+    match name
+        .to_str()
+        .and_then(|name| name.split_once(ALTERNATE_SEPARATOR))
+    {
+        Some((base, _)) => OsStr::new(base),
+        None => name,
+    }
+}
+
+/// Scores an entry name against this machine. Plain names score 0, and alternates score the sum
+/// of their condition weights, or fail with the reason they cannot be used here.
+fn score(name: &OsStr, vars: &BTreeMap<String, String>) -> Result<u32, String> {
+    let Some((_, conditions)) = name
+        .to_str()
+        .and_then(|name| name.split_once(ALTERNATE_SEPARATOR))
+    else {
+        return Ok(0);
+    };
+
+    let mut score = 0;
+    for condition in conditions.split(',') {
+        let condition = Condition::parse(condition)?;
+        if !condition.holds(vars) {
+            return Err(format!(
+                "`{}` does not match this machine",
+                condition.describe()
+            ));
+        }
+        score += condition.weight();
+    }
+    Ok(score)
+}
+
+/// Picks which of the entry names in one directory to use for each base name. Returns, in the
+/// order given, `Ok(base)` for the names to link or `Err(reason)` for the ones to skip.
+pub fn select(names: &[&OsStr], vars: &BTreeMap<String, String>) -> Vec<Result<OsString, String>> {
+    let scores: Vec<_> = names.iter().map(|name| score(name, vars)).collect();
+
+    // The first of the best-scoring names wins, so the pick is stable across runs
+    let mut best: HashMap<&OsStr, (usize, u32)> = HashMap::new();
+    for (i, (name, score)) in names.iter().zip(&scores).enumerate() {
+        if let Ok(score) = score {
+            best.entry(base_name(name))
+                .and_modify(|best| {
+                    if *score > best.1 {
+                        *best = (i, *score);
+                    }
+                })
+                .or_insert((i, *score));
+        }
+    }
+
+    names
+        .iter()
+        .zip(scores)
+        .enumerate()
+        .map(|(i, (name, score))| {
+            let base = base_name(name);
+            score.and_then(|_| match best[base] {
+                (winner, _) if winner == i => Ok(base.to_os_string()),
+                (winner, _) => Err(format!(
+                    "`{}` is a better match",
+                    names[winner].to_string_lossy()
+                )),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("os".to_string(), "linux".to_string()),
+            ("hostname".to_string(), "build01".to_string()),
+            ("user".to_string(), "alice".to_string()),
+        ])
+    }
+
+    fn select_names(names: &[&str]) -> Vec<Result<String, String>> {
+        let names: Vec<&OsStr> = names.iter().map(OsStr::new).collect();
+        select(&names, &vars())
+            .into_iter()
+            .map(|selected| selected.map(|name| name.to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_base_name() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(base_name(OsStr::new("gitconfig##os.linux")), "gitconfig");
+        assert_eq!(base_name(OsStr::new(".bashrc")), ".bashrc");
+        let not_utf8 = OsStr::from_bytes(b"caf\xe9##os.linux");
+        assert_eq!(base_name(not_utf8), not_utf8);
+    }
+
+    #[test]
+    fn test_select_prefers_the_most_specific_match() {
+        let names = [
+            "gitconfig##default",
+            "gitconfig##h.build01",
+            "gitconfig##os.linux",
+            "gitconfig##os.macos",
+            "vimrc",
+            "vimrc##os.linux,user.alice",
+            "zshrc##class.work",
+        ];
+
+        let selected = select_names(&names);
+
+        assert_eq!(
+            selected,
+            vec![
+                Err("`gitconfig##h.build01` is a better match".to_string()),
+                Ok("gitconfig".to_string()),
+                Err("`gitconfig##h.build01` is a better match".to_string()),
+                Err("`os.macos` does not match this machine".to_string()),
+                Err("`vimrc##os.linux,user.alice` is a better match".to_string()),
+                Ok("vimrc".to_string()),
+                Err("unknown condition `class.work`".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_falls_back_to_default() {
+        let names = ["gitconfig##default", "gitconfig##hostname.laptop"];
+
+        let selected = select_names(&names);
+
+        assert_eq!(selected[0], Ok("gitconfig".to_string()));
+        assert!(selected[1].is_err());
+    }
+}
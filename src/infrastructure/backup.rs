@@ -0,0 +1,422 @@
+use crate::constants::{BACKUPS_DIR, BACKUP_RUN_ID_FORMAT};
+use crate::domain::backup::BackupOperations;
+use crate::domain::shell::ShellExecutor;
+use crate::error::AppError;
+use crate::infrastructure::state::app_state_dir;
+use crate::infrastructure::target_fs::TargetFs;
+use crate::models::link::FileProcessResult;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+
+/// Keeps files displaced by the linker under `<root>/<run-id>/`, mirroring their absolute path.
+pub struct BackupStore {
+    root: PathBuf,
+    shell_executor: Arc<dyn ShellExecutor>,
+}
+
+impl BackupStore {
+    pub fn new(root: PathBuf, shell_executor: Arc<dyn ShellExecutor>) -> Self {
+        Self {
+            root,
+            shell_executor,
+        }
+    }
+
+    pub fn default_root() -> Result<PathBuf, AppError> {
+        Ok(app_state_dir()?.join(BACKUPS_DIR))
+    }
+
+    fn backup_path(&self, run_id: &str, path: &Path) -> PathBuf {
+        let relative: PathBuf = path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        self.root.join(run_id).join(relative)
+    }
+
+    /// Moves a backup back to where it came from, in place of the link that replaced it.
+    async fn put_back(
+        target_fs: &TargetFs,
+        backup_path: &Path,
+        original: &Path,
+        replaces_link: bool,
+    ) -> Result<(), AppError> {
+        if replaces_link {
+            target_fs.remove_file(original).await?;
+        }
+        target_fs.move_path(backup_path, original).await
+    }
+
+    /// Returns the overflow runs `<run-id>.<n>` of `run_id`, in the order they were made.
+    async fn overflow_runs(&self, run_id: &str) -> Result<Vec<String>, AppError> {
+        let mut overflows: Vec<(u32, String)> = self
+            .list_runs()
+            .await?
+            .into_iter()
+            .filter_map(|run| {
+                let n = run.strip_prefix(run_id)?.strip_prefix('.')?.parse().ok()?;
+                Some((n, run))
+            })
+            .collect();
+        overflows.sort();
+        Ok(overflows.into_iter().map(|(_, run)| run).collect())
+    }
+
+    /// Puts back everything backed up under `run_dir`. Each backed-up entry goes back whole in
+    /// place of whatever link replaced it; only directories that are still real directories in
+    /// the target are looked into, so a folded link is never followed. Paths in `restored` were
+    /// already put back from an earlier version, and are kept in this run.
+    async fn restore_run(
+        &self,
+        run_dir: &Path,
+        restored: &mut Vec<PathBuf>,
+        results: &mut Vec<FileProcessResult>,
+    ) -> Result<bool, AppError> {
+        let mut restored_all = true;
+        let mut pending = sorted_children(run_dir).await?;
+        while let Some(backup_path) = pending.pop() {
+            let relative = backup_path
+                .strip_prefix(run_dir)
+                .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+            let original = Path::new("/").join(relative);
+            if restored.iter().any(|path| original.starts_with(path)) {
+                restored_all = false;
+                results.push(FileProcessResult::Skipped(original));
+                continue;
+            }
+
+            let backup_is_dir = fs::symlink_metadata(&backup_path).await?.is_dir();
+            let replaces_link = match fs::symlink_metadata(&original).await {
+                Ok(metadata) if metadata.file_type().is_symlink() => true,
+                Ok(metadata) if metadata.is_dir() && backup_is_dir => {
+                    pending.extend(sorted_children(&backup_path).await?);
+                    continue;
+                }
+                Ok(_) => {
+                    // Never clobber a real file that appeared after the backup was taken
+                    restored_all = false;
+                    results.push(FileProcessResult::Skipped(original));
+                    continue;
+                }
+                Err(_) => false,
+            };
+
+            // Files backed up from a target linked with sudo go back the same way
+            let direct = TargetFs::direct();
+            match Self::put_back(&direct, &backup_path, &original, replaces_link).await {
+                Err(AppError::Io(e)) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    let sudo = TargetFs::sudo(self.shell_executor.clone());
+                    Self::put_back(&sudo, &backup_path, &original, replaces_link).await?
+                }
+                restored => restored?,
+            }
+            restored.push(original.clone());
+            results.push(FileProcessResult::Restored(original, backup_path));
+        }
+        Ok(restored_all)
+    }
+}
+
+/// Returns the entries of `dir` in reverse name order, to be popped in name order.
+async fn sorted_children(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let mut children = Vec::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        children.push(entry.path());
+    }
+    children.sort_by(|a, b| b.cmp(a));
+    Ok(children)
+}
+
+pub(crate) async fn move_path(from: &Path, to: &Path) -> Result<(), AppError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    if fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+
+    // rename(2) cannot cross filesystems, so fall back to copying, and clean up a partial copy
+    let existed = fs::symlink_metadata(to).await.is_ok();
+    if let Err(e) = copy_tree(from, to).await {
+        if !existed {
+            let _ = fs::remove_dir_all(to).await;
+            let _ = fs::remove_file(to).await;
+        }
+        return Err(e);
+    }
+    if fs::symlink_metadata(from).await?.is_dir() {
+        fs::remove_dir_all(from).await?;
+    } else {
+        fs::remove_file(from).await?;
+    }
+    Ok(())
+}
+
+/// Copies `from` to `to` along with everything in it, recreating symlinks rather than
+/// following them.
+fn copy_tree<'a>(from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<(), AppError>> {
+    Box::pin(async move {
+        let metadata = fs::symlink_metadata(from).await?;
+        if metadata.file_type().is_symlink() {
+            fs::symlink(fs::read_link(from).await?, to).await?;
+        } else if metadata.is_dir() {
+            fs::create_dir(to).await?;
+            let mut entries = fs::read_dir(from).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                copy_tree(&entry.path(), &to.join(entry.file_name())).await?;
+            }
+            fs::set_permissions(to, metadata.permissions()).await?;
+        } else {
+            fs::copy(from, to).await?;
+        }
+        Ok(())
+    })
+}
+
+#[async_trait]
+impl BackupOperations for BackupStore {
+    /// Returns the time of day as the ID, suffixed when a run of that second already exists.
+    fn new_run_id(&self) -> String {
+        let timestamp = chrono::Local::now()
+            .format(BACKUP_RUN_ID_FORMAT)
+            .to_string();
+        let mut run_id = timestamp.clone();
+        let mut n = 1;
+        while self.root.join(&run_id).exists() {
+            n += 1;
+            run_id = format!("{}-{}", timestamp, n);
+        }
+        run_id
+    }
+
+    /// Returns a free place to back `path` up to, creating its parent directory. Nothing is ever
+    /// overwritten: when `path` was already backed up in this run, the newer version goes into
+    /// an overflow run `<run-id>.<n>` that can be restored on its own.
+    async fn reserve(&self, run_id: &str, path: &Path) -> Result<PathBuf, AppError> {
+        let path = std::path::absolute(path)?;
+        let mut backup_path = self.backup_path(run_id, &path);
+        let mut n = 1;
+        while fs::symlink_metadata(&backup_path).await.is_ok() {
+            n += 1;
+            backup_path = self.backup_path(&format!("{}.{}", run_id, n), &path);
+        }
+
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        Ok(backup_path)
+    }
+
+    async fn restore(&self, run_id: &str) -> Result<Vec<FileProcessResult>, AppError> {
+        let run_dir = self.root.join(run_id);
+        if !run_dir.is_dir() {
+            return Err(AppError::BackupNotFound(run_id.to_string()));
+        }
+
+        // The base run holds what was there before the run, so it goes back first
+        let mut runs = vec![run_id.to_string()];
+        runs.extend(self.overflow_runs(run_id).await?);
+
+        let mut results = Vec::new();
+        let mut restored = Vec::new();
+        for run in runs {
+            let run_dir = self.root.join(run);
+            if self
+                .restore_run(&run_dir, &mut restored, &mut results)
+                .await?
+            {
+                fs::remove_dir_all(&run_dir).await?;
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn list_runs(&self) -> Result<Vec<String>, AppError> {
+        let mut runs = Vec::new();
+        let mut entries = match fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(runs),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                runs.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        runs.sort();
+
+        Ok(runs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::shell::executor::SystemShellExecutor;
+    use tempfile::TempDir;
+
+    fn shell() -> Arc<dyn ShellExecutor> {
+        Arc::new(SystemShellExecutor::new())
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let home = temp_dir.path().join("home");
+        fs::create_dir_all(&home).await?;
+        let bashrc = home.join(".bashrc");
+        fs::write(&bashrc, "hand edited").await?;
+
+        let store = BackupStore::new(temp_dir.path().join("backups"), shell());
+        let run_id = store.new_run_id();
+        let backup_path = store.reserve(&run_id, &bashrc).await?;
+        move_path(&bashrc, &backup_path).await?;
+
+        assert!(fs::symlink_metadata(&bashrc).await.is_err());
+        assert_eq!(fs::read_to_string(&backup_path).await?, "hand edited");
+        assert_eq!(store.list_runs().await?, vec![run_id.clone()]);
+
+        // Simulate the linker having put a symlink in place of the original
+        fs::symlink(temp_dir.path().join("dotfiles/.bashrc"), &bashrc).await?;
+
+        let results = store.restore(&run_id).await?;
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(&results[0], FileProcessResult::Restored(p, _) if p == &bashrc));
+        assert_eq!(fs::read_to_string(&bashrc).await?, "hand edited");
+        assert!(store.list_runs().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_keeps_every_version() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("file");
+        let store = BackupStore::new(temp_dir.path().join("backups"), shell());
+        let run_id = store.new_run_id();
+
+        fs::write(&file, "original").await?;
+        let first = store.reserve(&run_id, &file).await?;
+        move_path(&file, &first).await?;
+        fs::write(&file, "precious edits").await?;
+        let second = store.reserve(&run_id, &file).await?;
+        move_path(&file, &second).await?;
+
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).await?, "original");
+        assert_eq!(fs::read_to_string(&second).await?, "precious edits");
+        assert!(fs::symlink_metadata(&file).await.is_err());
+        assert_eq!(
+            store.list_runs().await?,
+            vec![run_id.clone(), format!("{}.2", run_id)]
+        );
+
+        // A later run never shares its directory with an earlier one
+        assert_ne!(store.new_run_id(), run_id);
+
+        // Restoring puts back what was there before the run, and keeps the later version
+        let results = store.restore(&run_id).await?;
+        assert!(matches!(&results[0], FileProcessResult::Restored(p, _) if p == &file));
+        assert!(matches!(&results[1], FileProcessResult::Skipped(p) if p == &file));
+        assert_eq!(fs::read_to_string(&file).await?, "original");
+        assert_eq!(store.list_runs().await?, vec![format!("{}.2", run_id)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_replaced_directory() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let nvim = temp_dir.path().join("home/.config/nvim");
+        let source = temp_dir.path().join("dotfiles/nvim");
+        fs::create_dir_all(&nvim).await?;
+        fs::create_dir_all(&source).await?;
+        fs::write(nvim.join("init.lua"), "mine").await?;
+        fs::write(nvim.join("local.lua"), "local").await?;
+        fs::write(source.join("init.lua"), "dotfiles").await?;
+
+        let store = BackupStore::new(temp_dir.path().join("backups"), shell());
+        let run_id = store.new_run_id();
+        move_path(&nvim, &store.reserve(&run_id, &nvim).await?).await?;
+
+        // Simulate the linker having folded the directory in place of the original
+        fs::symlink(&source, &nvim).await?;
+
+        let results = store.restore(&run_id).await?;
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(&results[0], FileProcessResult::Restored(p, _) if p == &nvim));
+        assert!(fs::symlink_metadata(&nvim).await?.is_dir());
+        assert_eq!(fs::read_to_string(nvim.join("init.lua")).await?, "mine");
+        assert_eq!(fs::read_to_string(nvim.join("local.lua")).await?, "local");
+        assert_eq!(
+            fs::read_to_string(source.join("init.lua")).await?,
+            "dotfiles"
+        );
+        assert!(fs::symlink_metadata(source.join("local.lua"))
+            .await
+            .is_err());
+        assert!(store.list_runs().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_does_not_clobber_regular_files() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("file");
+        let store = BackupStore::new(temp_dir.path().join("backups"), shell());
+
+        let run_id = store.new_run_id();
+        fs::write(&file, "original").await?;
+        move_path(&file, &store.reserve(&run_id, &file).await?).await?;
+        fs::write(&file, "newer").await?;
+
+        let results = store.restore(&run_id).await?;
+
+        assert!(matches!(&results[0], FileProcessResult::Skipped(p) if p == &file));
+        assert_eq!(fs::read_to_string(&file).await?, "newer");
+        assert_eq!(store.list_runs().await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_copy_tree() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("from");
+        fs::create_dir_all(from.join("nested")).await?;
+        fs::write(from.join("nested/file"), "content").await?;
+        fs::symlink("nested/file", from.join("link")).await?;
+
+        let to = temp_dir.path().join("to");
+        copy_tree(&from, &to).await?;
+
+        assert_eq!(fs::read_to_string(to.join("nested/file")).await?, "content");
+        assert_eq!(
+            fs::read_link(to.join("link")).await?,
+            PathBuf::from("nested/file")
+        );
+        assert!(from.join("nested/file").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_unknown_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = BackupStore::new(temp_dir.path().join("backups"), shell());
+
+        let result = store.restore("19700101-000000").await;
+        assert!(matches!(result, Err(AppError::BackupNotFound(_))));
+        assert!(store.list_runs().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use crate::constants::{DEFAULT_IGNORE_RULES, GITIGNORE_FILENAME, IGNORE_FILENAME};
+use crate::error::AppError;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Gitignore-style rules that apply to one directory of a source tree.
+///
+/// Rules are layered: the built-in defaults first, then the ignore files of every directory from
+/// the source root down. Deeper files take precedence, and `!pattern` re-includes a path that an
+/// earlier rule excluded.
+#[derive(Clone)]
+pub struct IgnoreRules {
+    layers: Vec<Arc<Gitignore>>,
+    use_gitignore: bool,
+}
+
+impl IgnoreRules {
+    /// Loads the defaults and the ignore files at the source root.
+    pub fn new(source: &Path, use_gitignore: bool) -> Result<Self, AppError> {
+        let mut builder = GitignoreBuilder::new(source);
+        for line in DEFAULT_IGNORE_RULES {
+            builder
+                .add_line(None, line)
+                .map_err(|e| AppError::IgnoreRule(e.to_string()))?;
+        }
+        let defaults = builder
+            .build()
+            .map_err(|e| AppError::IgnoreRule(e.to_string()))?;
+
+        Self {
+            layers: vec![Arc::new(defaults)],
+            use_gitignore,
+        }
+        .descend(source)
+    }
+
+    /// Returns the rules for `dir`, adding its own ignore files on top of the current ones.
+    pub fn descend(&self, dir: &Path) -> Result<Self, AppError> {
+        let mut files = Vec::new();
+        if self.use_gitignore {
+            files.push(dir.join(GITIGNORE_FILENAME));
+        }
+        // Added last so that it wins over .gitignore within the same directory
+        files.push(dir.join(IGNORE_FILENAME));
+
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for file in files.iter().filter(|file| file.is_file()) {
+            if let Some(e) = builder.add(file) {
+                return Err(AppError::IgnoreRule(e.to_string()));
+            }
+            found = true;
+        }
+
+        let mut rules = self.clone();
+        if found {
+            let layer = builder
+                .build()
+                .map_err(|e| AppError::IgnoreRule(e.to_string()))?;
+            rules.layers.push(Arc::new(layer));
+        }
+        Ok(rules)
+    }
+
+    /// Returns a description of the rule that excludes `path`, if any.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<String> {
+        for layer in self.layers.iter().rev() {
+            let path = path.strip_prefix(layer.path()).unwrap_or(path);
+            if path.has_root() {
+                continue;
+            }
+
+            match layer.matched_path_or_any_parents(path, is_dir) {
+                Match::None => continue,
+                Match::Whitelist(_) => return None,
+                Match::Ignore(glob) => {
+                    let origin = glob
+                        .from()
+                        .map(|from| from.display().to_string())
+                        .unwrap_or_else(|| "built-in defaults".to_string());
+                    return Some(format!("`{}` from {}", glob.original(), origin));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The `include` and `exclude` globs of one link entry, matched against paths relative to the
+/// source root. `*` stays within one directory and `**` crosses any number of them.
+pub struct EntryFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl EntryFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, AppError> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(glob_set(include)?)
+        };
+        Ok(Self {
+            include,
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    /// Returns whether `path` is left out. Directories are only left out when excluded, since
+    /// the files they hold may still be included.
+    pub fn rejects(&self, path: &Path, is_dir: bool) -> bool {
+        if self.exclude.is_match(path) {
+            return true;
+        }
+        match &self.include {
+            Some(include) if !is_dir => !include.is_match(path),
+            _ => false,
+        }
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, AppError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| AppError::IgnoreRule(e.to_string()))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| AppError::IgnoreRule(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn test_nested_rules_and_negation() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let source = temp_dir.path();
+        let nested = source.join("nested");
+        fs::create_dir_all(&nested).await?;
+        fs::write(source.join(IGNORE_FILENAME), "*.bak\nlocal/\n").await?;
+        fs::write(nested.join(IGNORE_FILENAME), "!keep.bak\n_*\n!_included\n").await?;
+
+        let root_rules = IgnoreRules::new(source, false)?;
+        let nested_rules = root_rules.descend(&nested)?;
+
+        assert!(root_rules.matched(&source.join("old.bak"), false).is_some());
+        assert!(root_rules.matched(&source.join("local"), true).is_some());
+        assert!(root_rules.matched(&source.join(".bashrc"), false).is_none());
+
+        assert!(nested_rules
+            .matched(&nested.join("old.bak"), false)
+            .is_some());
+        assert!(nested_rules
+            .matched(&nested.join("keep.bak"), false)
+            .is_none());
+        assert!(nested_rules
+            .matched(&nested.join("_included"), false)
+            .is_none());
+
+        let rule = nested_rules.matched(&nested.join("_draft"), false).unwrap();
+        assert!(rule.starts_with("`_*` from "));
+        assert!(rule.ends_with(&nested.join(IGNORE_FILENAME).display().to_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gitignore_is_optional() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let source = temp_dir.path();
+        fs::write(source.join(GITIGNORE_FILENAME), "secrets.env\n").await?;
+
+        let rules = IgnoreRules::new(source, false)?;
+        assert!(rules.matched(&source.join("secrets.env"), false).is_none());
+
+        let rules = IgnoreRules::new(source, true)?;
+        assert!(rules.matched(&source.join("secrets.env"), false).is_some());
+
+        // .widotsignore can re-include what .gitignore excludes
+        fs::write(source.join(IGNORE_FILENAME), "!secrets.env\n").await?;
+        let rules = IgnoreRules::new(source, true)?;
+        assert!(rules.matched(&source.join("secrets.env"), false).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let rules = IgnoreRules::new(Path::new("/nonexistent"), false).unwrap();
+
+        let rule = rules.matched(Path::new(".DS_Store"), false).unwrap();
+        assert_eq!(rule, "`.DS_Store` from built-in defaults");
+        assert!(rules
+            .matched(Path::new("some/path/.git/config"), false)
+            .is_some());
+        assert!(rules
+            .matched(Path::new("/nonexistent/_draft"), false)
+            .is_some());
+        assert!(rules.matched(Path::new(".bashrc"), false).is_none());
+    }
+
+    #[test]
+    fn test_entry_filter() -> Result<(), AppError> {
+        let filter = EntryFilter::new(
+            &["nvim/**".to_string(), "*.toml".to_string()],
+            &["nvim/spell".to_string()],
+        )?;
+
+        assert!(!filter.rejects(Path::new("nvim/init.lua"), false));
+        assert!(!filter.rejects(Path::new("starship.toml"), false));
+        assert!(!filter.rejects(Path::new("fish"), true));
+        assert!(filter.rejects(Path::new("fish/config.fish"), false));
+        assert!(filter.rejects(Path::new("fish/theme.toml"), false));
+        assert!(filter.rejects(Path::new("nvim/spell"), true));
+
+        let everything = EntryFilter::new(&[], &[])?;
+        assert!(!everything.rejects(Path::new("fish/config.fish"), false));
+
+        assert!(EntryFilter::new(&["a[".to_string()], &[]).is_err());
+        Ok(())
+    }
+}
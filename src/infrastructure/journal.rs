@@ -0,0 +1,365 @@
+use crate::constants::JOURNAL_STASH_SUFFIX;
+use crate::error::AppError;
+use crate::infrastructure::target_fs::TargetFs;
+use signal_hook::consts::SIGINT;
+use std::ffi::OsString;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use tokio::fs;
+
+/// One change a run made to the filesystem, with what it takes to undo it.
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    /// A file, link or directory that did not exist before.
+    Created(PathBuf),
+    /// Something moved aside instead of being deleted, removed for good once the run succeeds.
+    Stashed { path: PathBuf, stash: PathBuf },
+    /// Something moved elsewhere, such as into the backup store.
+    Moved { from: PathBuf, to: PathBuf },
+    /// An empty directory that was removed.
+    RemovedDir(PathBuf),
+    /// A path whose mode or owner was changed, with the ones it had before.
+    Permissions {
+        path: PathBuf,
+        mode: u32,
+        uid: u32,
+        gid: u32,
+    },
+}
+
+/// The changes of one run, in the order they were made, so that they can be undone. Each is
+/// undone through the [`TargetFs`] it was made with.
+#[derive(Debug, Default)]
+pub struct Journal {
+    changes: Vec<(Change, TargetFs)>,
+    target_fs: TargetFs,
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes and records the changes that follow through `target_fs`.
+    pub fn use_fs(&mut self, target_fs: TargetFs) {
+        self.target_fs = target_fs;
+    }
+
+    fn record(&mut self, change: Change) {
+        self.changes.push((change, self.target_fs.clone()));
+    }
+
+    /// Returns where `path` is moved aside to. It stays in the same directory, so the move is a
+    /// rename that cannot fail halfway.
+    pub fn stash_path(path: &Path) -> PathBuf {
+        let mut stash = path.as_os_str().to_os_string();
+        stash.push(OsString::from(format!(
+            "{}-{}",
+            JOURNAL_STASH_SUFFIX,
+            uuid::Uuid::new_v4().simple()
+        )));
+        PathBuf::from(stash)
+    }
+
+    pub fn created(&mut self, path: &Path) {
+        self.record(Change::Created(path.to_path_buf()));
+    }
+
+    pub fn stashed(&mut self, path: &Path, stash: &Path) {
+        self.record(Change::Stashed {
+            path: path.to_path_buf(),
+            stash: stash.to_path_buf(),
+        });
+    }
+
+    pub fn moved(&mut self, from: &Path, to: &Path) {
+        self.record(Change::Moved {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+    }
+
+    pub fn removed_dir(&mut self, path: &Path) {
+        self.record(Change::RemovedDir(path.to_path_buf()));
+    }
+
+    /// Records the mode and owner of `path`, which links are followed for, before they change.
+    pub async fn permissions(&mut self, path: &Path) -> Result<(), AppError> {
+        let metadata = fs::metadata(path).await?;
+        self.record(Change::Permissions {
+            path: path.to_path_buf(),
+            mode: metadata.mode() & 0o7777,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+        });
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns whether this run created `path`.
+    pub fn created_in_run(&self, path: &Path) -> bool {
+        self.changes
+            .iter()
+            .any(|(change, _)| matches!(change, Change::Created(created) if created == path))
+    }
+
+    /// Creates `path` and its missing ancestors, recording each directory created.
+    pub async fn create_dir_all(&mut self, path: &Path) -> Result<(), AppError> {
+        let mut missing = Vec::new();
+        for ancestor in path.ancestors() {
+            if fs::symlink_metadata(ancestor).await.is_ok() {
+                break;
+            }
+            missing.push(ancestor);
+        }
+        for dir in missing.into_iter().rev() {
+            self.target_fs.create_dir(dir).await?;
+            self.created(dir);
+        }
+        Ok(())
+    }
+
+    /// Moves `path` aside rather than deleting it, so that a rollback can put it back.
+    pub async fn remove(&mut self, path: &Path) -> Result<(), AppError> {
+        let stash = Self::stash_path(path);
+        self.target_fs.rename(path, &stash).await?;
+        self.stashed(path, &stash);
+        Ok(())
+    }
+
+    /// Removes a directory that holds nothing but what this run moved aside in it. It is moved
+    /// aside as a whole, so a rollback can put it back along with its contents.
+    pub async fn remove_dir(&mut self, path: &Path) -> Result<(), AppError> {
+        let mut only_stashes = true;
+        let mut empty = true;
+        let mut entries = fs::read_dir(path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            empty = false;
+            let entry_path = entry.path();
+            only_stashes &= self.changes.iter().any(
+                |(change, _)| matches!(change, Change::Stashed { stash, .. } if *stash == entry_path),
+            );
+        }
+
+        if empty || !only_stashes {
+            self.target_fs.remove_dir(path).await?;
+            self.removed_dir(path);
+            return Ok(());
+        }
+        self.remove(path).await
+    }
+
+    /// Keeps the changes and deletes what was moved aside.
+    pub async fn commit(self) -> Result<(), AppError> {
+        for (change, target_fs) in self.changes.into_iter().rev() {
+            if let Change::Stashed { stash, .. } = change {
+                // Stashes inside a directory that was moved aside later went with it
+                let metadata = match fs::symlink_metadata(&stash).await {
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    metadata => metadata?,
+                };
+                if metadata.is_dir() {
+                    target_fs.remove_dir_all(&stash).await?;
+                } else {
+                    target_fs.remove_file(&stash).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Undoes the changes, newest first. Every change is attempted, and the ones that could not
+    /// be undone are returned with the reason.
+    pub async fn rollback(self) -> Vec<(PathBuf, String)> {
+        let mut failures = Vec::new();
+        for (change, target_fs) in self.changes.into_iter().rev() {
+            let (path, undone) = match change {
+                Change::Created(path) => {
+                    let undone = remove_created(&target_fs, &path).await;
+                    (path, undone)
+                }
+                Change::Stashed { path, stash } => {
+                    let undone = target_fs.rename(&stash, &path).await;
+                    (path, undone)
+                }
+                Change::Moved { from, to } => {
+                    let undone = target_fs.move_path(&to, &from).await;
+                    (from, undone)
+                }
+                Change::RemovedDir(path) => {
+                    let undone = target_fs.create_dir(&path).await;
+                    (path, undone)
+                }
+                Change::Permissions {
+                    path,
+                    mode,
+                    uid,
+                    gid,
+                } => {
+                    let undone = target_fs.set_owner_and_mode(&path, uid, gid, mode).await;
+                    (path, undone)
+                }
+            };
+            if let Err(e) = undone {
+                failures.push((path, e.to_string()));
+            }
+        }
+        failures
+    }
+}
+
+/// Removes something a run created. Directories are empty by then, since whatever the run put
+/// in them was undone first.
+async fn remove_created(target_fs: &TargetFs, path: &Path) -> Result<(), AppError> {
+    match fs::symlink_metadata(path).await {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+        Ok(metadata) if metadata.is_dir() => target_fs.remove_dir(path).await,
+        Ok(_) => target_fs.remove_file(path).await,
+    }
+}
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static HANDLER: OnceLock<Result<(), String>> = OnceLock::new();
+
+/// Turns Ctrl-C into a flag for as long as it is alive, so that a run can stop between two
+/// changes and roll back. Outside of runs, Ctrl-C exits as usual.
+#[derive(Debug)]
+pub struct InterruptGuard(());
+
+impl InterruptGuard {
+    pub fn new() -> Result<Self, AppError> {
+        HANDLER
+            .get_or_init(|| {
+                // SAFETY: the handler only touches atomics and exits, which are async-signal-safe
+                unsafe {
+                    signal_hook::low_level::register(SIGINT, || {
+                        if RUNS.load(Ordering::SeqCst) > 0 {
+                            INTERRUPTED.store(true, Ordering::SeqCst);
+                        } else {
+                            signal_hook::low_level::exit(130);
+                        }
+                    })
+                }
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+            })
+            .clone()
+            .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+
+        RUNS.fetch_add(1, Ordering::SeqCst);
+        Ok(Self(()))
+    }
+
+    pub fn interrupted(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        if RUNS.fetch_sub(1, Ordering::SeqCst) == 1 {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::backup::move_path;
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_rollback_restores_the_target() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::write(root.join("original"), "original").await?;
+        fs::create_dir(root.join("empty")).await?;
+        fs::write(root.join("backed-up"), "backed up").await?;
+        fs::create_dir(root.join("linked")).await?;
+        fs::write(root.join("linked/file"), "linked").await?;
+        fs::write(root.join("private"), "private").await?;
+        fs::set_permissions(root.join("private"), Permissions::from_mode(0o644)).await?;
+
+        let mut journal = Journal::new();
+        journal.create_dir_all(&root.join("a/b")).await?;
+        fs::write(root.join("a/b/file"), "new").await?;
+        journal.created(&root.join("a/b/file"));
+        journal.remove(&root.join("original")).await?;
+        fs::symlink(root.join("a/b/file"), root.join("original")).await?;
+        journal.created(&root.join("original"));
+        fs::remove_dir(root.join("empty")).await?;
+        journal.removed_dir(&root.join("empty"));
+        move_path(&root.join("backed-up"), &root.join("backups/backed-up")).await?;
+        journal.moved(&root.join("backed-up"), &root.join("backups/backed-up"));
+        journal.remove(&root.join("linked/file")).await?;
+        journal.remove_dir(&root.join("linked")).await?;
+        journal.permissions(&root.join("private")).await?;
+        fs::set_permissions(root.join("private"), Permissions::from_mode(0o600)).await?;
+
+        assert!(journal.rollback().await.is_empty());
+
+        assert!(fs::symlink_metadata(root.join("a")).await.is_err());
+        assert_eq!(fs::read_to_string(root.join("original")).await?, "original");
+        assert!(fs::symlink_metadata(root.join("original")).await?.is_file());
+        assert!(root.join("empty").is_dir());
+        assert_eq!(
+            fs::read_to_string(root.join("linked/file")).await?,
+            "linked"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("backed-up")).await?,
+            "backed up"
+        );
+
+        let mut names = Vec::new();
+        let mut entries = fs::read_dir(root).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            names.push(entry.file_name());
+        }
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "backed-up",
+                "backups",
+                "empty",
+                "linked",
+                "original",
+                "private"
+            ]
+        );
+        let metadata = fs::metadata(root.join("private")).await?;
+        assert_eq!(metadata.mode() & 0o7777, 0o644);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commit_deletes_the_stash() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::create_dir(root.join("dir")).await?;
+        fs::write(root.join("dir/file"), "content").await?;
+
+        let mut journal = Journal::new();
+        journal.remove(&root.join("dir/file")).await?;
+        journal.remove_dir(&root.join("dir")).await?;
+        assert!(!journal.created_in_run(&root.join("dir")));
+        journal.commit().await?;
+
+        let mut entries = fs::read_dir(root).await?;
+        assert!(entries.next_entry().await?.is_none());
+
+        Ok(())
+    }
+}
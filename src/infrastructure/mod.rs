@@ -1,6 +1,16 @@
+pub mod alternate;
+pub mod backup;
 pub mod fs;
+pub mod ignore;
+pub mod journal;
 pub mod link;
 pub mod os;
 pub mod path;
+pub mod permission;
 pub mod prompt;
+pub mod secret;
 pub mod shell;
+pub mod state;
+pub mod target_fs;
+pub mod template;
+pub mod watch;
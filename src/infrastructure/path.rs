@@ -25,7 +25,7 @@ impl PathOperations for PathExpander {
         let expanded_path = if path.starts_with("~") {
             self.get_home_dir().await?.join(
                 path.strip_prefix("~")
-                    .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?,
+                    .map_err(|e| AppError::Io(std::io::Error::other(e)))?,
             )
         } else {
             path.to_path_buf()
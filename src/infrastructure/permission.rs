@@ -0,0 +1,325 @@
+use crate::constants::PRIVATE_DIRS;
+use crate::domain::permission::PermissionOperations;
+use crate::error::AppError;
+use crate::models::config::Permission;
+use crate::models::link::FileProcessResult;
+use crate::models::status::LinkStatus;
+use async_trait::async_trait;
+use globset::GlobBuilder;
+use nix::unistd::{Gid, Group, Uid, User};
+use std::collections::BTreeMap;
+use std::fs::Permissions;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use walkdir::WalkDir;
+
+/// Applies `[[permission]]` rules to the paths they match in a target directory.
+#[derive(Debug, Default)]
+pub struct PermissionManager;
+
+impl PermissionManager {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Owner {
+    name: String,
+    uid: Uid,
+    gid: Option<Gid>,
+}
+
+/// What the last rule matching a path wants for it.
+#[derive(Debug)]
+struct Wanted {
+    path: PathBuf,
+    mode: u32,
+    owner: Option<Owner>,
+}
+
+/// A path whose mode or owner differs from what its rule wants.
+#[derive(Debug)]
+struct Mismatch {
+    wanted: Wanted,
+    mode: Option<u32>,
+    owner: Option<String>,
+}
+
+fn parse_owner(owner: &str) -> Result<Owner, AppError> {
+    let (user, group) = match owner.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (owner, None),
+    };
+    let uid = User::from_name(user)
+        .map_err(|e| AppError::Permission(e.to_string()))?
+        .ok_or_else(|| AppError::Permission(format!("unknown user `{}`", user)))?
+        .uid;
+    let gid = match group {
+        Some(group) => Some(
+            Group::from_name(group)
+                .map_err(|e| AppError::Permission(e.to_string()))?
+                .ok_or_else(|| AppError::Permission(format!("unknown group `{}`", group)))?
+                .gid,
+        ),
+        None => None,
+    };
+    Ok(Owner {
+        name: owner.to_string(),
+        uid,
+        gid,
+    })
+}
+
+/// Returns the leading components of a glob that contain no wildcards, which is where the paths
+/// it can match are found.
+fn literal_prefix(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .iter()
+        .take_while(|component| {
+            !component
+                .to_string_lossy()
+                .contains(['*', '?', '[', '{', '\\'])
+        })
+        .collect()
+}
+
+/// Finds the paths the rules match. A path matched by several rules gets the last one.
+fn wanted_paths(target: &Path, rules: &[Permission]) -> Result<Vec<Wanted>, AppError> {
+    let mut wanted = BTreeMap::new();
+    for rule in rules {
+        let mode = rule.mode()?;
+        let owner = rule.owner.as_deref().map(parse_owner).transpose()?;
+        let pattern = rule.path.strip_prefix("~/").unwrap_or(&rule.path);
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| AppError::Permission(e.to_string()))?
+            .compile_matcher();
+
+        let prefix = literal_prefix(pattern);
+        let mut walker = WalkDir::new(target.join(&prefix)).sort_by_file_name();
+        if prefix == Path::new(pattern) {
+            walker = walker.max_depth(0);
+        }
+        // A missing prefix simply matches nothing
+        for entry in walker.into_iter().filter_map(Result::ok) {
+            let Ok(relative) = entry.path().strip_prefix(target) else {
+                continue;
+            };
+            if glob.is_match(relative) {
+                wanted.insert(
+                    entry.path().to_path_buf(),
+                    Wanted {
+                        path: entry.path().to_path_buf(),
+                        mode,
+                        owner: owner.clone(),
+                    },
+                );
+            }
+        }
+    }
+    Ok(wanted.into_values().collect())
+}
+
+fn owner_name(uid: u32, gid: u32, with_group: bool) -> String {
+    let user = User::from_uid(Uid::from_raw(uid))
+        .ok()
+        .flatten()
+        .map_or_else(|| uid.to_string(), |user| user.name);
+    if !with_group {
+        return user;
+    }
+    let group = Group::from_gid(Gid::from_raw(gid))
+        .ok()
+        .flatten()
+        .map_or_else(|| gid.to_string(), |group| group.name);
+    format!("{}:{}", user, group)
+}
+
+/// Compares the matched paths with their rules. Links are followed, since the mode of a link
+/// itself means nothing.
+async fn mismatches(target: &Path, rules: &[Permission]) -> Result<Vec<Mismatch>, AppError> {
+    let target = target.to_path_buf();
+    let rules = rules.to_vec();
+    let wanted = tokio::task::spawn_blocking(move || wanted_paths(&target, &rules))
+        .await
+        .map_err(|e| AppError::Io(e.into()))??;
+
+    let mut mismatches = Vec::new();
+    for wanted in wanted {
+        let Ok(metadata) = fs::metadata(&wanted.path).await else {
+            continue;
+        };
+        let actual_mode = metadata.mode() & 0o7777;
+        let mode = (actual_mode != wanted.mode).then_some(actual_mode);
+        let owner = wanted.owner.as_ref().and_then(|owner| {
+            let uid_differs = owner.uid.as_raw() != metadata.uid();
+            let gid_differs = owner.gid.is_some_and(|gid| gid.as_raw() != metadata.gid());
+            (uid_differs || gid_differs)
+                .then(|| owner_name(metadata.uid(), metadata.gid(), owner.gid.is_some()))
+        });
+        if mode.is_some() || owner.is_some() {
+            mismatches.push(Mismatch {
+                wanted,
+                mode,
+                owner,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Returns the files under `~/.ssh` and `~/.gnupg` that the group or others can read, with their
+/// modes. Public keys are meant to be shared and are left out.
+pub async fn exposed_paths(target: &Path) -> Vec<(PathBuf, u32)> {
+    let roots: Vec<PathBuf> = PRIVATE_DIRS.iter().map(|dir| target.join(dir)).collect();
+    tokio::task::spawn_blocking(move || {
+        let mut exposed = Vec::new();
+        for root in roots {
+            for entry in WalkDir::new(root)
+                .follow_links(true)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(Result::ok)
+            {
+                let is_public_key = entry.path().extension().is_some_and(|ext| ext == "pub");
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let mode = metadata.mode() & 0o7777;
+                if mode & 0o044 != 0 && !is_public_key {
+                    exposed.push((entry.path().to_path_buf(), mode));
+                }
+            }
+        }
+        exposed
+    })
+    .await
+    .unwrap_or_default()
+}
+
+#[async_trait]
+impl PermissionOperations for PermissionManager {
+    async fn check(
+        &self,
+        target: &Path,
+        rules: &[Permission],
+    ) -> Result<Vec<LinkStatus>, AppError> {
+        let mut statuses = Vec::new();
+        for mismatch in mismatches(target, rules).await? {
+            let wanted = mismatch.wanted;
+            if let Some(actual) = mismatch.mode {
+                statuses.push(LinkStatus::WrongMode(
+                    wanted.path.clone(),
+                    wanted.mode,
+                    actual,
+                ));
+            }
+            if let (Some(actual), Some(owner)) = (mismatch.owner, wanted.owner) {
+                statuses.push(LinkStatus::WrongOwner(wanted.path, owner.name, actual));
+            }
+        }
+        Ok(statuses)
+    }
+
+    async fn apply(
+        &self,
+        target: &Path,
+        rules: &[Permission],
+    ) -> Result<Vec<FileProcessResult>, AppError> {
+        let mut results = Vec::new();
+        for mismatch in mismatches(target, rules).await? {
+            let wanted = mismatch.wanted;
+            if let (Some(_), Some(owner)) = (mismatch.owner, &wanted.owner) {
+                std::os::unix::fs::chown(
+                    &wanted.path,
+                    Some(owner.uid.as_raw()),
+                    owner.gid.map(Gid::as_raw),
+                )?;
+                results.push(FileProcessResult::OwnerChanged(
+                    wanted.path.clone(),
+                    owner.name.clone(),
+                ));
+            }
+            // After changing the owner, which can clear setuid and setgid bits
+            if mismatch.mode.is_some() {
+                fs::set_permissions(&wanted.path, Permissions::from_mode(wanted.mode)).await?;
+                results.push(FileProcessResult::ModeChanged(wanted.path, wanted.mode));
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn rule(path: &str, mode: &str) -> Permission {
+        Permission {
+            path: path.to_string(),
+            mode: mode.to_string(),
+            owner: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_and_apply_permissions() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let target = temp_dir.path();
+        let ssh = target.join(".ssh");
+        std::fs::create_dir(&ssh)?;
+        std::fs::set_permissions(&ssh, Permissions::from_mode(0o755))?;
+        for name in ["config", "id_ed25519", "id_ed25519.pub"] {
+            std::fs::write(ssh.join(name), "")?;
+            std::fs::set_permissions(ssh.join(name), Permissions::from_mode(0o644))?;
+        }
+        std::fs::write(target.join(".bashrc"), "")?;
+
+        let rules = vec![
+            rule("~/.ssh", "700"),
+            rule(".ssh/*", "600"),
+            rule(".ssh/*.pub", "644"),
+        ];
+        let manager = PermissionManager::new();
+        let statuses = manager.check(target, &rules).await?;
+        assert_eq!(
+            statuses,
+            vec![
+                LinkStatus::WrongMode(ssh.clone(), 0o700, 0o755),
+                LinkStatus::WrongMode(ssh.join("config"), 0o600, 0o644),
+                LinkStatus::WrongMode(ssh.join("id_ed25519"), 0o600, 0o644),
+            ]
+        );
+        assert_eq!(
+            exposed_paths(target).await,
+            vec![
+                (ssh.clone(), 0o755),
+                (ssh.join("config"), 0o644),
+                (ssh.join("id_ed25519"), 0o644),
+            ]
+        );
+
+        let results = manager.apply(target, &rules).await?;
+        assert_eq!(results.len(), 3);
+        assert!(manager.check(target, &rules).await?.is_empty());
+        assert!(exposed_paths(target).await.is_empty());
+
+        // Owning a file as yourself is never a mismatch
+        let user = User::from_uid(Uid::current()).unwrap().unwrap();
+        let owned = Permission {
+            owner: Some(user.name),
+            ..rule(".bashrc", "644")
+        };
+        std::fs::set_permissions(target.join(".bashrc"), Permissions::from_mode(0o644))?;
+        assert!(manager.check(target, &[owned]).await?.is_empty());
+
+        let result = manager.check(target, &[rule(".ssh", "999")]).await;
+        assert!(matches!(result, Err(AppError::Permission(_))));
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use crate::domain::prompt::PromptOperations;
 use crate::error::AppError;
 use async_trait::async_trait;
-use inquire::Confirm;
+use inquire::{Confirm, Select};
 
 #[derive(Debug)]
 pub struct Prompt {
@@ -30,7 +30,19 @@ impl PromptOperations for Prompt {
             Confirm::new(message)
                 .with_default(false)
                 .prompt()
-                .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))
+                .map_err(|e| AppError::Io(std::io::Error::other(e)))
+        }
+    }
+
+    async fn select(&self, message: &str, choices: &[String]) -> Result<usize, AppError> {
+        if self.force_yes {
+            println!("{}", message);
+            Ok(0)
+        } else {
+            Select::new(message, choices.to_vec())
+                .raw_prompt()
+                .map(|choice| choice.index)
+                .map_err(|e| AppError::Io(std::io::Error::other(e)))
         }
     }
 }
@@ -52,6 +64,7 @@ mod test {
         #[async_trait]
         impl PromptOperations for Prompt {
             async fn confirm_action(&self, message: &str) -> Result<bool, AppError>;
+            async fn select(&self, message: &str, choices: &[String]) -> Result<usize, AppError>;
         }
     }
 
@@ -118,6 +131,14 @@ mod test {
         assert!(result);
     }
 
+    #[tokio::test]
+    async fn test_select_force_yes_picks_first_choice() {
+        let prompt = Prompt::new(true);
+        let choices = vec!["backup".to_string(), "skip".to_string()];
+        let result = prompt.select("Pick one", &choices).await.unwrap();
+        assert_eq!(result, 0);
+    }
+
     proptest! {
         #[test]
         fn test_confirm_action_with_various_messages(message in "[a-zA-Z0-9 ]{1,50}") {
@@ -0,0 +1,131 @@
+use crate::constants::{APP_NAME, IDENTITY_FILENAME};
+use crate::domain::secret::SecretOperations;
+use crate::error::AppError;
+use age::{Decryptor, Encryptor, IdentityFile, NoCallbacks};
+use async_trait::async_trait;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Encrypts and decrypts secrets with the age identities in a local file, such as one made by
+/// `age-keygen -o ~/.config/widots/identity.txt`.
+#[derive(Debug)]
+pub struct AgeSecrets {
+    identity_path: PathBuf,
+}
+
+impl AgeSecrets {
+    pub fn new(identity_path: PathBuf) -> Self {
+        Self { identity_path }
+    }
+
+    pub fn default_path() -> Result<PathBuf, AppError> {
+        let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+        Ok(home.join(".config").join(APP_NAME).join(IDENTITY_FILENAME))
+    }
+
+    async fn identity_file(&self) -> Result<IdentityFile<NoCallbacks>, String> {
+        let content = fs::read(&self.identity_path).await.map_err(|e| {
+            format!(
+                "cannot read identity file {}: {}",
+                self.identity_path.display(),
+                e
+            )
+        })?;
+        IdentityFile::from_buffer(content.as_slice()).map_err(|e| {
+            format!(
+                "invalid identity file {}: {}",
+                self.identity_path.display(),
+                e
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl SecretOperations for AgeSecrets {
+    async fn decrypt(&self, path: &Path) -> Result<Vec<u8>, AppError> {
+        let fail = |reason: String| AppError::Decrypt(path.to_path_buf(), reason);
+
+        let ciphertext = fs::read(path).await?;
+        let identities = self
+            .identity_file()
+            .await
+            .map_err(fail)?
+            .into_identities()
+            .map_err(|e| fail(e.to_string()))?;
+
+        let decryptor =
+            Decryptor::new_buffered(ciphertext.as_slice()).map_err(|e| fail(e.to_string()))?;
+        let mut reader = decryptor
+            .decrypt(identities.iter().map(|identity| identity.as_ref()))
+            .map_err(|e| fail(e.to_string()))?;
+        let mut plaintext = Vec::new();
+        reader.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    async fn encrypt(&self, path: &Path) -> Result<Vec<u8>, AppError> {
+        let fail = |reason: String| AppError::Encrypt(path.to_path_buf(), reason);
+
+        // Encrypt to the identities we decrypt with, so every secret can be read back
+        let plaintext = fs::read(path).await?;
+        let recipients = self
+            .identity_file()
+            .await
+            .map_err(fail)?
+            .to_recipients()
+            .map_err(|e| fail(e.to_string()))?;
+
+        let encryptor =
+            Encryptor::with_recipients(recipients.iter().map(|recipient| recipient.as_ref() as _))
+                .map_err(|e| fail(e.to_string()))?;
+        let mut ciphertext = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut ciphertext)?;
+        writer.write_all(&plaintext)?;
+        writer.finish()?;
+        Ok(ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_encrypt_and_decrypt() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let identity = age::x25519::Identity::generate();
+        let identity_path = temp_dir.path().join(IDENTITY_FILENAME);
+        std::fs::write(
+            &identity_path,
+            format!(
+                "# created: today\n{}\n",
+                identity.to_string().expose_secret()
+            ),
+        )?;
+        let secrets = AgeSecrets::new(identity_path);
+
+        let netrc = temp_dir.path().join(".netrc");
+        std::fs::write(&netrc, "machine example.com password hunter2")?;
+        let ciphertext = secrets.encrypt(&netrc).await?;
+        assert!(!String::from_utf8_lossy(&ciphertext).contains("hunter2"));
+
+        let encrypted = temp_dir.path().join(".netrc.age");
+        std::fs::write(&encrypted, &ciphertext)?;
+        assert_eq!(
+            secrets.decrypt(&encrypted).await?,
+            b"machine example.com password hunter2"
+        );
+
+        // Without the identity file nothing can be decrypted
+        let result = AgeSecrets::new(temp_dir.path().join("missing.txt"))
+            .decrypt(&encrypted)
+            .await;
+        assert!(matches!(result, Err(AppError::Decrypt(path, _)) if path == encrypted));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::constants::{APP_NAME, STATE_FILENAME};
+use crate::domain::state::StateOperations;
+use crate::error::AppError;
+use crate::models::state::LinkState;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// Returns `$XDG_STATE_HOME/widots`, falling back to `~/.local/state/widots`.
+pub(crate) fn app_state_dir() -> Result<PathBuf, AppError> {
+    let state_dir = dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("state")))
+        .ok_or(AppError::DirectoryNotFound)?;
+    Ok(state_dir.join(APP_NAME))
+}
+
+/// Keeps the link state in a TOML file.
+#[derive(Debug)]
+pub struct StateStore {
+    path: PathBuf,
+}
+
+impl StateStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn default_path() -> Result<PathBuf, AppError> {
+        Ok(app_state_dir()?.join(STATE_FILENAME))
+    }
+}
+
+#[async_trait]
+impl StateOperations for StateStore {
+    async fn load(&self) -> Result<LinkState, AppError> {
+        match fs::read_to_string(&self.path).await {
+            Ok(content) => Ok(toml::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LinkState::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, state: &LinkState) -> Result<(), AppError> {
+        let content = toml::to_string(state).map_err(|e| AppError::State(e.to_string()))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // Write next to the file and rename, so an interrupted run never leaves it truncated
+        let temp_path = self.path.with_extension("toml.tmp");
+        fs::write(&temp_path, content).await?;
+        fs::rename(&temp_path, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::state::EntryKind;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_load_and_save() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let store = StateStore::new(temp_dir.path().join("widots").join(STATE_FILENAME));
+
+        // A missing file is an empty state
+        assert_eq!(store.load().await?, LinkState::default());
+
+        let mut state = LinkState::default();
+        state.record(
+            EntryKind::Link,
+            Path::new("/home/.bashrc"),
+            Path::new("/dotfiles/.bashrc"),
+        );
+        store.save(&state).await?;
+
+        assert_eq!(store.load().await?, state);
+        assert!(!temp_dir
+            .path()
+            .join("widots")
+            .join("state.toml.tmp")
+            .exists());
+
+        Ok(())
+    }
+}
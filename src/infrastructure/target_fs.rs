@@ -0,0 +1,260 @@
+use crate::domain::shell::ShellExecutor;
+use crate::error::AppError;
+use crate::infrastructure::backup::move_path;
+use std::fmt;
+use std::fs::Permissions;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tokio::fs;
+
+/// Makes the changes the linker writes into a target directory, either directly or through
+/// `sudo` for targets the user cannot write to. Reading always happens as the user.
+#[derive(Clone, Default)]
+pub struct TargetFs {
+    sudo: Option<Arc<dyn ShellExecutor>>,
+}
+
+impl fmt::Debug for TargetFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TargetFs")
+            .field("sudo", &self.is_sudo())
+            .finish()
+    }
+}
+
+impl TargetFs {
+    pub fn direct() -> Self {
+        Self::default()
+    }
+
+    pub fn sudo(shell_executor: Arc<dyn ShellExecutor>) -> Self {
+        Self {
+            sudo: Some(shell_executor),
+        }
+    }
+
+    pub fn is_sudo(&self) -> bool {
+        self.sudo.is_some()
+    }
+
+    async fn run(&self, command: &[&str], paths: &[&Path]) -> Result<(), AppError> {
+        let shell_executor = self.sudo.as_ref().expect("only called through sudo");
+        let mut args = command.to_vec();
+        args.push("--");
+        for path in paths {
+            args.push(
+                path.to_str()
+                    .ok_or_else(|| AppError::NonUtf8Path(path.to_path_buf()))?,
+            );
+        }
+        shell_executor.execute("sudo", &args).await?;
+        Ok(())
+    }
+
+    pub async fn create_dir(&self, path: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["mkdir"], &[path]).await,
+            None => Ok(fs::create_dir(path).await?),
+        }
+    }
+
+    pub async fn rename(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["mv", "-f"], &[from, to]).await,
+            None => Ok(fs::rename(from, to).await?),
+        }
+    }
+
+    /// Moves `from` to `to` even across filesystems. The parent of `to` has to exist already.
+    pub async fn move_path(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["mv", "-f"], &[from, to]).await,
+            None => move_path(from, to).await,
+        }
+    }
+
+    pub async fn remove_file(&self, path: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["rm", "-f"], &[path]).await,
+            None => Ok(fs::remove_file(path).await?),
+        }
+    }
+
+    pub async fn remove_dir(&self, path: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["rmdir"], &[path]).await,
+            None => Ok(fs::remove_dir(path).await?),
+        }
+    }
+
+    pub async fn remove_dir_all(&self, path: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["rm", "-rf"], &[path]).await,
+            None => Ok(fs::remove_dir_all(path).await?),
+        }
+    }
+
+    pub async fn symlink(&self, original: &Path, link: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["ln", "-s"], &[original, link]).await,
+            None => Ok(fs::symlink(original, link).await?),
+        }
+    }
+
+    pub async fn hard_link(&self, original: &Path, link: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["ln"], &[original, link]).await,
+            None => Ok(fs::hard_link(original, link).await?),
+        }
+    }
+
+    pub async fn copy(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => self.run(&["cp"], &[from, to]).await,
+            None => {
+                fs::copy(from, to).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Gives `path` the owner `uid`:`gid` and then `mode`, since changing the owner can clear the
+    /// setuid and setgid bits.
+    pub async fn set_owner_and_mode(
+        &self,
+        path: &Path,
+        uid: u32,
+        gid: u32,
+        mode: u32,
+    ) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => {
+                self.run(&["chown", &format!("{}:{}", uid, gid)], &[path])
+                    .await?;
+                self.run(&["chmod", &format!("{:o}", mode)], &[path]).await
+            }
+            None => {
+                std::os::unix::fs::chown(path, Some(uid), Some(gid))?;
+                Ok(fs::set_permissions(path, Permissions::from_mode(mode)).await?)
+            }
+        }
+    }
+
+    /// Writes `contents` to `path`. Through `sudo` the file becomes readable by everyone, as
+    /// files under `/etc` usually are.
+    pub async fn write(&self, path: &Path, contents: &[u8]) -> Result<(), AppError> {
+        match self.sudo {
+            Some(_) => {
+                let mut temp_file = NamedTempFile::new()?;
+                temp_file.as_file_mut().write_all(contents)?;
+                self.run(&["install", "-m", "644"], &[temp_file.path(), path])
+                    .await
+            }
+            None => Ok(fs::write(path, contents).await?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::mock;
+    use mockall::Sequence;
+    use std::process::Output;
+    use tempfile::TempDir;
+
+    mock! {
+        ShellExecutor {}
+        #[async_trait::async_trait]
+        impl ShellExecutor for ShellExecutor {
+            async fn execute<'a>(&self, command: &'a str, args: &'a [&'a str]) -> Result<String, AppError>;
+            async fn output<'a>(&self, command: &'a str, args: &'a [&'a str]) -> Result<Output, AppError>;
+            fn stderr(&self, output: &Output) -> String;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sudo_runs_every_change_through_sudo() -> Result<(), AppError> {
+        let mut mock_shell = MockShellExecutor::new();
+        let mut sequence = Sequence::new();
+        for expected in [
+            vec!["mkdir", "--", "/etc/xdg/autostart"],
+            vec![
+                "ln",
+                "-s",
+                "--",
+                "/src/app.desktop",
+                "/etc/xdg/autostart/app.desktop",
+            ],
+            vec!["mv", "-f", "--", "/etc/xdg/a", "/etc/xdg/b"],
+            vec!["rm", "-rf", "--", "/etc/xdg/b"],
+        ] {
+            mock_shell
+                .expect_execute()
+                .withf(move |command, args| command == "sudo" && args == expected.as_slice())
+                .times(1)
+                .in_sequence(&mut sequence)
+                .returning(|_, _| Ok(String::new()));
+        }
+
+        let target_fs = TargetFs::sudo(Arc::new(mock_shell));
+        assert!(target_fs.is_sudo());
+        target_fs
+            .create_dir(Path::new("/etc/xdg/autostart"))
+            .await?;
+        target_fs
+            .symlink(
+                Path::new("/src/app.desktop"),
+                Path::new("/etc/xdg/autostart/app.desktop"),
+            )
+            .await?;
+        target_fs
+            .rename(Path::new("/etc/xdg/a"), Path::new("/etc/xdg/b"))
+            .await?;
+        target_fs.remove_dir_all(Path::new("/etc/xdg/b")).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sudo_rejects_paths_that_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut mock_shell = MockShellExecutor::new();
+        mock_shell.expect_execute().times(0);
+
+        let target_fs = TargetFs::sudo(Arc::new(mock_shell));
+        let path = Path::new(OsStr::from_bytes(b"/etc/caf\xe9"));
+        let result = target_fs.remove_file(path).await;
+        assert!(matches!(result, Err(AppError::NonUtf8Path(p)) if p == path));
+    }
+
+    #[tokio::test]
+    async fn test_direct_changes_the_filesystem() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        let target_fs = TargetFs::direct();
+
+        target_fs.write(&root.join("file"), b"content").await?;
+        target_fs
+            .copy(&root.join("file"), &root.join("copy"))
+            .await?;
+        target_fs
+            .symlink(&root.join("file"), &root.join("link"))
+            .await?;
+        target_fs
+            .rename(&root.join("copy"), &root.join("moved"))
+            .await?;
+        target_fs.remove_file(&root.join("link")).await?;
+
+        assert_eq!(fs::read_to_string(root.join("moved")).await?, "content");
+        assert!(fs::symlink_metadata(root.join("link")).await.is_err());
+        assert!(!target_fs.is_sudo());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::domain::os::OSOperations;
+use crate::domain::path::PathOperations;
+use crate::error::AppError;
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.-]*)\s*\}\}").unwrap());
+
+/// Collects the variables every template can use: `os`, `hostname`, `user` and `home`.
+pub async fn builtin_vars(
+    os_detector: &dyn OSOperations,
+    path_operations: &dyn PathOperations,
+) -> Result<BTreeMap<String, String>, AppError> {
+    let hostname = whoami::fallible::hostname().map_err(AppError::Io)?;
+    let home = path_operations.get_home_dir().await?;
+
+    Ok(BTreeMap::from([
+        ("os".to_string(), os_detector.get_os().await?),
+        ("hostname".to_string(), hostname),
+        ("user".to_string(), whoami::username()),
+        ("home".to_string(), home.display().to_string()),
+    ]))
+}
+
+/// Replaces every `{{ name }}` in `template` with its value. Unknown names are an error rather
+/// than an empty string, so that a typo cannot silently produce a broken dotfile.
+pub fn render(template: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut missing = Vec::new();
+    let rendered = PLACEHOLDER.replace_all(template, |caps: &Captures| match vars.get(&caps[1]) {
+        Some(value) => value.clone(),
+        None => {
+            missing.push(caps[1].to_string());
+            String::new()
+        }
+    });
+
+    if !missing.is_empty() {
+        return Err(format!("undefined variables: {}", missing.join(", ")));
+    }
+    Ok(rendered.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use mockall::mock;
+    use std::path::{Path, PathBuf};
+
+    mock! {
+        pub OSOperations {}
+        #[async_trait]
+        impl OSOperations for OSOperations {
+            async fn get_os(&self) -> Result<String, AppError>;
+        }
+    }
+
+    mock! {
+        pub PathOperations {}
+        #[async_trait]
+        impl PathOperations for PathOperations {
+            async fn expand_tilde(&self, path: &Path) -> Result<PathBuf, AppError>;
+            async fn parse_path(&self, path: &Path) -> Result<PathBuf, AppError>;
+            async fn get_home_dir(&self) -> Result<PathBuf, AppError>;
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let vars = BTreeMap::from([
+            ("user".to_string(), "alice".to_string()),
+            ("git.email".to_string(), "alice@example.com".to_string()),
+        ]);
+
+        let rendered = render(
+            "name = {{user}}\nemail = {{ git.email }}\n{ literal }",
+            &vars,
+        );
+        assert_eq!(
+            rendered.unwrap(),
+            "name = alice\nemail = alice@example.com\n{ literal }"
+        );
+
+        let result = render("{{ user }} {{ email }} {{ shell }}", &vars);
+        assert_eq!(result.unwrap_err(), "undefined variables: email, shell");
+    }
+
+    #[tokio::test]
+    async fn test_builtin_vars() {
+        let mut mock_os = MockOSOperations::new();
+        mock_os
+            .expect_get_os()
+            .returning(|| Ok("linux".to_string()));
+        let mut mock_path = MockPathOperations::new();
+        mock_path
+            .expect_get_home_dir()
+            .returning(|| Ok(PathBuf::from("/home/alice")));
+
+        let vars = builtin_vars(&mock_os, &mock_path).await.unwrap();
+
+        assert_eq!(vars["os"], "linux");
+        assert_eq!(vars["home"], "/home/alice");
+        assert_eq!(vars["user"], whoami::username());
+        assert!(!vars["hostname"].is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::domain::watch::WatchOperations;
+use crate::error::AppError;
+use async_trait::async_trait;
+use log::warn;
+use notify::{Event, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Watches a directory tree through the platform's file watcher, inotify on Linux.
+#[derive(Debug, Default)]
+pub struct NotifyWatcher;
+
+impl NotifyWatcher {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl WatchOperations for NotifyWatcher {
+    async fn watch(
+        &self,
+        path: &Path,
+        debounce: Duration,
+    ) -> Result<mpsc::Receiver<Vec<PathBuf>>, AppError> {
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = event_tx.send(event);
+        })
+        .map_err(|e| AppError::Watch(e.to_string()))?;
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(|e| AppError::Watch(e.to_string()))?;
+
+        let (batch_tx, batch_rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            // Dropping the watcher stops it, so it lives as long as this task
+            let _watcher = watcher;
+            while let Some(event) = event_rx.recv().await {
+                let mut changed = BTreeSet::new();
+                collect_changes(event, &mut changed);
+
+                // An editor saving a file or git checking out a branch fires a burst of events;
+                // wait until the tree is quiet and report them together
+                while let Ok(Some(event)) = tokio::time::timeout(debounce, event_rx.recv()).await {
+                    collect_changes(event, &mut changed);
+                }
+
+                if changed.is_empty() {
+                    continue;
+                }
+                if batch_tx.send(changed.into_iter().collect()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(batch_rx)
+    }
+}
+
+/// Adds the paths an event changed. Reads are not changes, and neither is anything inside a
+/// `.git` directory, which git rewrites on every commit and fetch.
+fn collect_changes(event: notify::Result<Event>, changed: &mut BTreeSet<PathBuf>) {
+    match event {
+        Ok(event) if !event.kind.is_access() => {
+            changed.extend(event.paths.into_iter().filter(|path| {
+                !path
+                    .components()
+                    .any(|component| component.as_os_str() == ".git")
+            }));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Watch error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_watch_batches_changes() -> Result<(), AppError> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().canonicalize()?;
+        std::fs::create_dir(root.join(".git"))?;
+
+        let mut changes = NotifyWatcher::new()
+            .watch(&root, Duration::from_millis(200))
+            .await?;
+        std::fs::write(root.join(".bashrc"), "alias ll='ls -l'")?;
+        std::fs::write(root.join(".vimrc"), "set number")?;
+        std::fs::write(root.join(".git").join("HEAD"), "ref: refs/heads/main")?;
+
+        let batch = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+            .await
+            .expect("no changes reported")
+            .expect("watcher stopped");
+        assert!(batch.contains(&root.join(".bashrc")));
+        assert!(batch.contains(&root.join(".vimrc")));
+        assert!(!batch.iter().any(|path| path.starts_with(root.join(".git"))));
+
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
+use crate::error::AppError;
+use crate::models::link::{ConflictStrategy, LinkMethod, LinkOptions};
 use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -7,17 +10,231 @@ pub struct Config {
     pub link: Option<Vec<Link>>,
     #[serde(default)]
     pub provision: Option<Vec<Provision>>,
+    #[serde(default)]
+    pub permission: Option<Vec<Permission>>,
+    #[serde(default)]
+    pub package: Option<Vec<Package>>,
+    /// User-defined values for `.tmpl` files, on top of the built-in ones.
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    #[serde(default)]
+    pub profile: BTreeMap<String, Profile>,
+}
+
+/// A named set of links, provisions and packages, such as `work` or `ci`.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct Profile {
+    /// Profiles whose entries apply in this one as well.
+    #[serde(default)]
+    pub extends: Vec<String>,
+    /// Hostnames that use this profile when none is given.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+impl Config {
+    /// Returns the profile listing `hostname` in its `hosts`, if any.
+    pub fn host_profile(&self, hostname: &str) -> Result<Option<String>, AppError> {
+        let mut matching = self
+            .profile
+            .iter()
+            .filter(|(_, profile)| profile.hosts.iter().any(|host| host == hostname))
+            .map(|(name, _)| name.clone());
+        match (matching.next(), matching.next()) {
+            (Some(first), Some(second)) => Err(AppError::Profile(format!(
+                "host `{}` is listed by both `{}` and `{}`",
+                hostname, first, second
+            ))),
+            (first, _) => Ok(first),
+        }
+    }
+
+    /// Keeps the links, provisions and packages that apply in `profile` or a profile it extends,
+    /// along with those without `profiles`, which are all that is left without a profile.
+    pub fn select(mut self, profile: Option<&str>) -> Result<Config, AppError> {
+        let active: BTreeSet<String> = match profile {
+            Some(name) => self
+                .extended_profiles(name)?
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            None => BTreeSet::new(),
+        };
+        let applies = |profiles: &[String]| {
+            profiles.is_empty() || profiles.iter().any(|name| active.contains(name))
+        };
+
+        if let Some(links) = &mut self.link {
+            links.retain(|link| applies(&link.profiles));
+        }
+        if let Some(provisions) = &mut self.provision {
+            provisions.retain(|provision| applies(&provision.profiles));
+        }
+        if let Some(packages) = &mut self.package {
+            packages.retain(|package| applies(&package.profiles));
+        }
+        Ok(self)
+    }
+
+    /// Returns `name` along with every profile it extends, directly or not.
+    fn extended_profiles<'a>(&'a self, name: &'a str) -> Result<BTreeSet<&'a str>, AppError> {
+        if !self.is_known_profile(name) {
+            return Err(AppError::Profile(format!("unknown profile `{}`", name)));
+        }
+
+        let mut active = BTreeSet::new();
+        let mut stack = vec![(name, vec![name])];
+        while let Some((name, chain)) = stack.pop() {
+            active.insert(name);
+            let extends = self
+                .profile
+                .get(name)
+                .map(|profile| profile.extends.as_slice());
+            for parent in extends.unwrap_or_default() {
+                if chain.contains(&parent.as_str()) {
+                    return Err(AppError::Profile(format!(
+                        "`{}` extends itself: {} -> {}",
+                        parent,
+                        chain.join(" -> "),
+                        parent
+                    )));
+                }
+                if !self.is_known_profile(parent) {
+                    return Err(AppError::Profile(format!(
+                        "`{}` extends unknown profile `{}`",
+                        name, parent
+                    )));
+                }
+                let mut chain = chain.clone();
+                chain.push(parent);
+                stack.push((parent, chain));
+            }
+        }
+        Ok(active)
+    }
+
+    /// A profile exists once it has a `[profile.<name>]` table or an entry lists it.
+    fn is_known_profile(&self, name: &str) -> bool {
+        let listed = |profiles: &[String]| profiles.iter().any(|profile| profile == name);
+        self.profile.contains_key(name)
+            || self
+                .link
+                .iter()
+                .flatten()
+                .any(|link| listed(&link.profiles))
+            || self
+                .provision
+                .iter()
+                .flatten()
+                .any(|provision| listed(&provision.profiles))
+            || self
+                .package
+                .iter()
+                .flatten()
+                .any(|package| listed(&package.profiles))
+    }
+
+    /// Returns the packages of every `[[package]]` entry, as `list` picks them out of each.
+    pub fn packages(&self, list: impl Fn(&Package) -> &[String]) -> Vec<String> {
+        let packages = self.package.iter().flatten();
+        packages
+            .flat_map(|package| list(package).to_vec())
+            .collect()
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct Link {
     pub location: PathBuf,
+    /// Where to link into, such as `~/.config` or `/etc/xdg`, instead of the home directory.
+    #[serde(default)]
+    pub target: Option<PathBuf>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Make the changes through `sudo`, for targets the user cannot write to.
+    #[serde(default)]
+    pub sudo: bool,
+    #[serde(default)]
+    pub gitignore: bool,
+    #[serde(default)]
+    pub fold: bool,
+    #[serde(default)]
+    pub relative: bool,
+    #[serde(default)]
+    pub on_conflict: ConflictStrategy,
+    #[serde(default)]
+    pub method: LinkMethod,
+    #[serde(default)]
+    pub deny_chars: Vec<char>,
+    /// Only link in these profiles, or in every one when empty.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+impl Link {
+    pub fn options(&self) -> LinkOptions {
+        LinkOptions {
+            gitignore: self.gitignore,
+            fold: self.fold,
+            relative: self.relative,
+            on_conflict: self.on_conflict,
+            method: self.method,
+            deny_chars: self.deny_chars.clone(),
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            sudo: self.sudo,
+            ..Default::default()
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// The mode, and optionally the owner, that paths in the target directory should have.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Permission {
+    /// A glob matched against paths relative to the target directory, such as `.ssh/*`.
+    pub path: String,
+    /// An octal mode such as `"600"`.
+    pub mode: String,
+    /// A user name, or `user:group`.
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+impl Permission {
+    pub fn mode(&self) -> Result<u32, AppError> {
+        u32::from_str_radix(&self.mode, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o7777)
+            .ok_or_else(|| AppError::Permission(format!("invalid mode `{}`", self.mode)))
+    }
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct Provision {
     pub mode: String,
     pub script: String,
+    /// Only run in these profiles, or in every one when empty.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+/// Packages to install with `brew import` and `vscode import`, on top of the exported lists.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct Package {
+    /// Homebrew formulas.
+    #[serde(default)]
+    pub brew: Vec<String>,
+    /// Homebrew casks.
+    #[serde(default)]
+    pub cask: Vec<String>,
+    /// VSCode extension IDs.
+    #[serde(default)]
+    pub vscode: Vec<String>,
+    /// Only install in these profiles, or in every one when empty.
+    #[serde(default)]
+    pub profiles: Vec<String>,
 }
 
 #[cfg(test)]
@@ -30,6 +247,34 @@ mod test {
         [[link]]
         location = "/path/to/dotfiles"
 
+        [[link]]
+        location = "/path/to/work-dotfiles"
+        gitignore = true
+        fold = true
+        relative = true
+        on_conflict = "skip"
+        method = "copy"
+        deny_chars = [":", "\\"]
+
+        [[link]]
+        location = "/path/to/system"
+        target = "/etc/xdg"
+        include = ["autostart/**"]
+        exclude = ["autostart/*.bak"]
+        sudo = true
+
+        [vars]
+        email = "alice@example.com"
+
+        [[permission]]
+        path = ".ssh"
+        mode = "700"
+        owner = "alice:staff"
+
+        [[permission]]
+        path = ".ssh/*"
+        mode = "0600"
+
         [[provision]]
         mode = "macos"
         script = "echo 'Hello, macOS!'"
@@ -41,16 +286,161 @@ mod test {
 
         let config: Config = toml::from_str(toml_str).unwrap();
 
-        assert_eq!(config.link.clone().unwrap().len(), 1);
+        assert_eq!(config.link.clone().unwrap().len(), 3);
         assert_eq!(config.provision.clone().unwrap().len(), 2);
 
-        let link = &config.link.unwrap()[0];
-        assert_eq!(link.location, PathBuf::from("/path/to/dotfiles"));
+        let links = config.link.unwrap();
+        assert_eq!(links[0].location, PathBuf::from("/path/to/dotfiles"));
+        assert_eq!(links[0].options(), LinkOptions::default());
+        assert!(links[1].options().gitignore);
+        assert!(links[1].options().fold);
+        assert!(links[1].options().relative);
+        assert_eq!(links[1].on_conflict, ConflictStrategy::Skip);
+        assert_eq!(links[1].options().method, LinkMethod::Copy);
+        assert_eq!(links[1].options().deny_chars, vec![':', '\\']);
+        assert_eq!(links[1].target, None);
+        assert!(!links[1].sudo);
+        assert_eq!(links[2].target, Some(PathBuf::from("/etc/xdg")));
+        assert_eq!(links[2].options().include, vec!["autostart/**"]);
+        assert_eq!(links[2].options().exclude, vec!["autostart/*.bak"]);
+        assert!(links[2].sudo);
+
+        assert_eq!(config.vars["email"], "alice@example.com");
+
+        let permissions = config.permission.unwrap();
+        assert_eq!(permissions[0].mode().unwrap(), 0o700);
+        assert_eq!(permissions[0].owner.as_deref(), Some("alice:staff"));
+        assert_eq!(permissions[1].mode().unwrap(), 0o600);
+        assert_eq!(permissions[1].owner, None);
 
         let provisions = &config.provision.unwrap();
         assert_eq!(provisions[0].mode, "macos");
         assert_eq!(provisions[0].script, "echo 'Hello, macOS!'");
         assert_eq!(provisions[1].mode, "linux");
         assert_eq!(provisions[1].script, "echo 'Hello, Linux!'");
+        assert!(config.profile.is_empty());
+    }
+
+    fn profiles_config() -> Config {
+        toml::from_str(
+            r#"
+        [profile.base]
+
+        [profile.work]
+        extends = ["base"]
+        hosts = ["work-laptop"]
+
+        [profile.ci]
+        hosts = ["build01"]
+
+        [[link]]
+        location = "/path/to/dotfiles"
+
+        [[link]]
+        location = "/path/to/base-dotfiles"
+        profiles = ["base"]
+
+        [[link]]
+        location = "/path/to/work-dotfiles"
+        profiles = ["work"]
+
+        [[provision]]
+        mode = "linux"
+        script = "echo 'Hello, CI!'"
+        profiles = ["ci", "personal"]
+
+        [[package]]
+        brew = ["git"]
+        vscode = ["rust-lang.rust-analyzer"]
+
+        [[package]]
+        brew = ["awscli"]
+        cask = ["slack"]
+        profiles = ["work"]
+    "#,
+        )
+        .unwrap()
+    }
+
+    fn locations(config: &Config) -> Vec<&str> {
+        let links = config.link.iter().flatten();
+        links.map(|link| link.location.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_select_profile() {
+        let config = profiles_config();
+        assert_eq!(config.profile["work"].extends, vec!["base"]);
+
+        let work = config.clone().select(Some("work")).unwrap();
+        assert_eq!(
+            locations(&work),
+            [
+                "/path/to/dotfiles",
+                "/path/to/base-dotfiles",
+                "/path/to/work-dotfiles"
+            ]
+        );
+        assert!(work.provision.clone().unwrap().is_empty());
+        assert_eq!(work.packages(|package| &package.brew), ["git", "awscli"]);
+        assert_eq!(work.packages(|package| &package.cask), ["slack"]);
+
+        let base = config.clone().select(Some("base")).unwrap();
+        assert_eq!(
+            locations(&base),
+            ["/path/to/dotfiles", "/path/to/base-dotfiles"]
+        );
+
+        // A profile only named by an entry needs no table of its own
+        let personal = config.clone().select(Some("personal")).unwrap();
+        assert_eq!(locations(&personal), ["/path/to/dotfiles"]);
+        assert_eq!(personal.provision.unwrap().len(), 1);
+
+        let none = config.clone().select(None).unwrap();
+        assert_eq!(locations(&none), ["/path/to/dotfiles"]);
+        assert_eq!(none.packages(|package| &package.brew), ["git"]);
+        assert!(none.packages(|package| &package.cask).is_empty());
+        assert_eq!(
+            none.packages(|package| &package.vscode),
+            ["rust-lang.rust-analyzer"]
+        );
+
+        assert!(matches!(
+            config.select(Some("wrok")),
+            Err(AppError::Profile(message)) if message == "unknown profile `wrok`"
+        ));
+    }
+
+    #[test]
+    fn test_select_profile_rejects_cycles() {
+        let mut config = profiles_config();
+        config.profile.get_mut("base").unwrap().extends = vec!["work".to_string()];
+        assert!(matches!(
+            config.clone().select(Some("work")),
+            Err(AppError::Profile(message)) if message == "`work` extends itself: work -> base -> work"
+        ));
+
+        config.profile.get_mut("base").unwrap().extends = vec!["missing".to_string()];
+        assert!(matches!(
+            config.select(Some("work")),
+            Err(AppError::Profile(message)) if message == "`base` extends unknown profile `missing`"
+        ));
+    }
+
+    #[test]
+    fn test_host_profile() {
+        let mut config = profiles_config();
+        assert_eq!(
+            config.host_profile("work-laptop").unwrap().as_deref(),
+            Some("work")
+        );
+        assert_eq!(config.host_profile("laptop").unwrap(), None);
+
+        config.profile.get_mut("base").unwrap().hosts = vec!["build01".to_string()];
+        assert!(matches!(
+            config.host_profile("build01"),
+            Err(AppError::Profile(message))
+                if message == "host `build01` is listed by both `base` and `ci`"
+        ));
     }
 }
@@ -0,0 +1,11 @@
+use std::path::PathBuf;
+
+/// A managed file whose target no longer has the content its source gives it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    /// The content the target would get from the source, rendered if it is a template.
+    pub expected: Vec<u8>,
+    pub actual: Vec<u8>,
+}
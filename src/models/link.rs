@@ -1,3 +1,7 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -6,4 +10,130 @@ pub enum FileProcessResult {
     Created(PathBuf),
     Materialized(PathBuf, PathBuf),
     Skipped(PathBuf),
+    BackedUp(PathBuf, PathBuf),
+    Restored(PathBuf, PathBuf),
+    Unlinked(PathBuf, PathBuf),
+    Removed(PathBuf),
+    Conflicted(PathBuf, ConflictStrategy),
+    Added(PathBuf, PathBuf),
+    Rendered(PathBuf, PathBuf),
+    Decrypted(PathBuf, PathBuf),
+    Copied(PathBuf, PathBuf),
+    Encrypted(PathBuf, PathBuf),
+    ModeChanged(PathBuf, u32),
+    OwnerChanged(PathBuf, String),
+    Failed(PathBuf, MaterializeError),
+}
+
+/// Why a link could not be replaced by a copy of what it points to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterializeError {
+    /// The link, or a link inside a linked directory, points to nothing.
+    Dangling(PathBuf),
+    /// A linked directory contains a link back to one of its own ancestors.
+    Loop(PathBuf),
+    Io(String),
+}
+
+impl fmt::Display for MaterializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterializeError::Dangling(path) => write!(f, "{} does not exist", path.display()),
+            MaterializeError::Loop(path) => {
+                write!(
+                    f,
+                    "{} links back to a directory being copied",
+                    path.display()
+                )
+            }
+            MaterializeError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<std::io::Error> for MaterializeError {
+    fn from(error: std::io::Error) -> Self {
+        MaterializeError::Io(error.to_string())
+    }
+}
+
+/// How to handle a target path that already exists and is not a link to the source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// Leave the existing file alone and do not link
+    Skip,
+    /// Delete the existing file
+    Overwrite,
+    /// Move the existing file to the backup store
+    #[default]
+    Backup,
+    /// Move the existing file into the source, replacing its version there
+    Adopt,
+    /// Ask for each conflicting file
+    Ask,
+}
+
+impl fmt::Display for ConflictStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConflictStrategy::Skip => "skip",
+            ConflictStrategy::Overwrite => "overwrite",
+            ConflictStrategy::Backup => "backup",
+            ConflictStrategy::Adopt => "adopt",
+            ConflictStrategy::Ask => "ask",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How a source file is placed in the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMethod {
+    /// Link to the source file
+    #[default]
+    Symlink,
+    /// Copy the source file and keep the copy in sync
+    Copy,
+    /// Hard link to the source file
+    Hardlink,
+}
+
+impl fmt::Display for LinkMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LinkMethod::Symlink => "symlink",
+            LinkMethod::Copy => "copy",
+            LinkMethod::Hardlink => "hardlink",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Per-run settings for linking a source tree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkOptions {
+    /// Also skip files excluded by `.gitignore` files in the source tree.
+    pub gitignore: bool,
+    /// Link whole directories when the target has none yet, like GNU stow.
+    pub fold: bool,
+    /// Point links at their source with a path relative to the link's directory.
+    pub relative: bool,
+    pub on_conflict: ConflictStrategy,
+    pub method: LinkMethod,
+    /// Values substituted into `.tmpl` files.
+    pub vars: BTreeMap<String, String>,
+    /// Characters that make a file name invalid, so the file is skipped.
+    pub deny_chars: Vec<char>,
+    /// Globs of the files to link, relative to the source. Empty means every file.
+    pub include: Vec<String>,
+    /// Globs of the files and directories never to link, relative to the source.
+    pub exclude: Vec<String>,
+    /// Never prompt, leaving a directory in place rather than asking to replace it.
+    pub unattended: bool,
+    /// Make the changes through `sudo`, for targets the user cannot write to.
+    pub sudo: bool,
+    /// Linking for a `[[link]]` of the config in `load`, which prunes it once the entry is gone.
+    pub loaded: bool,
 }
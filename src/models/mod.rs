@@ -1,2 +1,6 @@
 pub mod config;
+pub mod diff;
 pub mod link;
+pub mod plan;
+pub mod state;
+pub mod status;
@@ -0,0 +1,102 @@
+use crate::models::config::Provision;
+use crate::models::link::LinkOptions;
+use crate::models::status::LinkStatus;
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    InvalidFilename(String),
+    Ignored,
+    Alternate(String),
+    Excluded,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::InvalidFilename(e) => write!(f, "invalid file name: {}", e),
+            SkipReason::Ignored => write!(f, "ignored"),
+            SkipReason::Alternate(reason) => write!(f, "alternate not used: {}", reason),
+            SkipReason::Excluded => write!(f, "left out by include or exclude"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkAction {
+    CreateDir(PathBuf),
+    Link(PathBuf, PathBuf),
+    Replace(PathBuf, PathBuf),
+    Skip(PathBuf, SkipReason),
+    Unlink(PathBuf, PathBuf),
+    Remove(PathBuf),
+    RemoveDir(PathBuf),
+    Render(PathBuf, PathBuf),
+    Decrypt(PathBuf, PathBuf),
+}
+
+impl fmt::Display for LinkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkAction::CreateDir(path) => write!(f, "Create directory: {}", path.display()),
+            LinkAction::Link(src, dst) => {
+                write!(f, "Link: {} -> {}", src.display(), dst.display())
+            }
+            LinkAction::Replace(src, dst) => {
+                write!(f, "Replace: {} -> {}", src.display(), dst.display())
+            }
+            LinkAction::Skip(path, reason) => {
+                write!(f, "Skip: {} ({})", path.display(), reason)
+            }
+            LinkAction::Unlink(link, src) => {
+                write!(f, "Unlink: {} (-> {})", link.display(), src.display())
+            }
+            LinkAction::Remove(path) => write!(f, "Remove: {}", path.display()),
+            LinkAction::RemoveDir(path) => write!(f, "Remove directory: {}", path.display()),
+            LinkAction::Render(src, dst) => {
+                write!(f, "Render: {} -> {}", src.display(), dst.display())
+            }
+            LinkAction::Decrypt(src, dst) => {
+                write!(f, "Decrypt: {} -> {}", src.display(), dst.display())
+            }
+        }
+    }
+}
+
+/// The filesystem changes a link run would make, computed without touching the target.
+#[derive(Debug, Clone, Default)]
+pub struct LinkPlan {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub actions: Vec<LinkAction>,
+    pub options: LinkOptions,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadPlan {
+    /// The profile that chose the links and provisions, if any.
+    pub profile: Option<String>,
+    pub links: Vec<LinkPlan>,
+    /// The removal of what `load` linked from sources that are no longer configured.
+    pub stale: LinkPlan,
+    pub permissions: Vec<LinkStatus>,
+    pub provisions: Vec<Provision>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_action_display() {
+        let action = LinkAction::Link(
+            PathBuf::from("/src/.bashrc"),
+            PathBuf::from("/home/.bashrc"),
+        );
+        assert_eq!(action.to_string(), "Link: /src/.bashrc -> /home/.bashrc");
+
+        let action = LinkAction::Skip(PathBuf::from("/src/.DS_Store"), SkipReason::Ignored);
+        assert_eq!(action.to_string(), "Skip: /src/.DS_Store (ignored)");
+    }
+}
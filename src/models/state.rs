@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Link,
+    Rendered,
+    Decrypted,
+    Copy,
+    Hardlink,
+    Dir,
+}
+
+/// Something the linker put in a target directory, and the source path it came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub kind: EntryKind,
+    pub path: PathBuf,
+    pub source: PathBuf,
+    /// The content hash of a copied or hard linked file when it was placed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Whether it was placed through `sudo`, so that removing it takes `sudo` too.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sudo: bool,
+    /// Whether `load` placed it for a `[[link]]` of the config, so that `load` removes it once
+    /// that entry is gone.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub loaded: bool,
+}
+
+/// Everything the linker has created and not removed again, across all runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkState {
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<StateEntry>,
+}
+
+impl LinkState {
+    /// Records `path`, replacing whatever was recorded for it before. TOML cannot hold paths that
+    /// are not valid UTF-8, so those are not recorded and never pruned.
+    pub fn record(&mut self, kind: EntryKind, path: &Path, source: &Path) {
+        self.forget(path);
+        if path.to_str().is_none() || source.to_str().is_none() {
+            return;
+        }
+        self.entries.push(StateEntry {
+            kind,
+            path: path.to_path_buf(),
+            source: source.to_path_buf(),
+            hash: None,
+            sudo: false,
+            loaded: false,
+        });
+    }
+
+    /// Records a copied or hard linked file along with the hash of the content it was given.
+    pub fn record_copy(&mut self, kind: EntryKind, path: &Path, source: &Path, hash: String) {
+        self.record(kind, path, source);
+        if let Some(entry) = self.entries.last_mut().filter(|entry| entry.path == path) {
+            entry.hash = Some(hash);
+        }
+    }
+
+    /// Marks what `source` produced in `target` as placed through `sudo`.
+    pub fn mark_sudo(&mut self, source: &Path, target: &Path) {
+        for entry in &mut self.entries {
+            if entry.source.starts_with(source) && entry.path.starts_with(target) {
+                entry.sudo = true;
+            }
+        }
+    }
+
+    /// Marks what `source` produced in `target` as placed by `load`.
+    pub fn mark_loaded(&mut self, source: &Path, target: &Path) {
+        for entry in &mut self.entries {
+            if entry.source.starts_with(source) && entry.path.starts_with(target) {
+                entry.loaded = true;
+            }
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&StateEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    pub fn forget(&mut self, path: &Path) {
+        self.entries.retain(|entry| entry.path != path);
+    }
+
+    /// Returns the entries that `source` produced in `target`.
+    pub fn entries_from<'a>(
+        &'a self,
+        source: &'a Path,
+        target: &'a Path,
+    ) -> impl Iterator<Item = &'a StateEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.source.starts_with(source) && entry.path.starts_with(target))
+    }
+
+    /// Returns the entries `load` placed whose source is in none of `sources`.
+    pub fn loaded_outside<'a>(
+        &'a self,
+        sources: &'a [PathBuf],
+    ) -> impl Iterator<Item = &'a StateEntry> {
+        self.entries.iter().filter(move |entry| {
+            entry.loaded
+                && !sources
+                    .iter()
+                    .any(|source| entry.source.starts_with(source))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn test_record_and_forget() {
+        let mut state = LinkState::default();
+        state.record(
+            EntryKind::Link,
+            Path::new("/home/.config"),
+            Path::new("/dotfiles/.config"),
+        );
+        state.record(
+            EntryKind::Dir,
+            Path::new("/home/.config"),
+            Path::new("/dotfiles/.config"),
+        );
+        state.record(
+            EntryKind::Link,
+            Path::new("/home/.bashrc"),
+            Path::new("/work-dotfiles/.bashrc"),
+        );
+
+        let entries: Vec<_> = state
+            .entries_from(Path::new("/dotfiles"), Path::new("/home"))
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, EntryKind::Dir);
+
+        state.forget(Path::new("/home/.config"));
+        assert_eq!(state.entries.len(), 1);
+
+        let not_utf8 = Path::new(OsStr::from_bytes(b"/home/caf\xe9"));
+        state.record(EntryKind::Link, not_utf8, Path::new("/dotfiles/cafe"));
+        assert_eq!(state.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_state_round_trips_through_toml() {
+        let mut state = LinkState::default();
+        state.record(
+            EntryKind::Rendered,
+            Path::new("/home/.gitconfig"),
+            Path::new("/dotfiles/.gitconfig.tmpl"),
+        );
+
+        state.record_copy(
+            EntryKind::Copy,
+            Path::new("/home/.ssh/config"),
+            Path::new("/dotfiles/.ssh/config"),
+            "abc123".to_string(),
+        );
+
+        let serialized = toml::to_string(&state).unwrap();
+        assert!(serialized.contains("[[entry]]"));
+        assert!(serialized.contains("kind = \"rendered\""));
+        assert!(serialized.contains("hash = \"abc123\""));
+        assert_eq!(serialized.matches("hash").count(), 1);
+        assert!(!serialized.contains("sudo"));
+        assert!(!serialized.contains("loaded"));
+
+        state.mark_sudo(Path::new("/dotfiles/.ssh"), Path::new("/home"));
+        state.mark_loaded(Path::new("/dotfiles"), Path::new("/home/.gitconfig"));
+        let serialized = toml::to_string(&state).unwrap();
+        assert_eq!(serialized.matches("sudo = true").count(), 1);
+        assert_eq!(serialized.matches("loaded = true").count(), 1);
+        assert_eq!(toml::from_str::<LinkState>(&serialized).unwrap(), state);
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkStatus {
+    Linked(PathBuf, PathBuf),
+    Missing(PathBuf, PathBuf),
+    NotLinked(PathBuf, PathBuf),
+    WrongTarget(PathBuf, PathBuf, PathBuf),
+    Broken(PathBuf, PathBuf, PathBuf),
+    Stray(PathBuf, PathBuf),
+    Rendered(PathBuf, PathBuf),
+    Decrypted(PathBuf, PathBuf),
+    Copied(PathBuf, PathBuf),
+    Edited(PathBuf, PathBuf),
+    Outdated(PathBuf, PathBuf),
+    WrongMode(PathBuf, u32, u32),
+    WrongOwner(PathBuf, String, String),
+}
+
+impl LinkStatus {
+    pub fn is_drift(&self) -> bool {
+        !matches!(
+            self,
+            LinkStatus::Linked(_, _)
+                | LinkStatus::Rendered(_, _)
+                | LinkStatus::Decrypted(_, _)
+                | LinkStatus::Copied(_, _)
+        )
+    }
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkStatus::Linked(src, dst) => {
+                write!(f, "Linked: {} -> {}", dst.display(), src.display())
+            }
+            LinkStatus::Missing(src, dst) => write!(
+                f,
+                "Missing: {} (expected link to {})",
+                dst.display(),
+                src.display()
+            ),
+            LinkStatus::NotLinked(src, dst) => write!(
+                f,
+                "Not a link: {} (expected link to {})",
+                dst.display(),
+                src.display()
+            ),
+            LinkStatus::WrongTarget(src, dst, actual) => write!(
+                f,
+                "Wrong link: {} -> {} (expected {})",
+                dst.display(),
+                actual.display(),
+                src.display()
+            ),
+            LinkStatus::Broken(src, dst, actual) => write!(
+                f,
+                "Broken link: {} -> {} (expected {})",
+                dst.display(),
+                actual.display(),
+                src.display()
+            ),
+            LinkStatus::Stray(dst, src) => write!(
+                f,
+                "Stray link: {} -> {} (source no longer exists)",
+                dst.display(),
+                src.display()
+            ),
+            LinkStatus::Rendered(src, dst) => {
+                write!(f, "Rendered: {} (from {})", dst.display(), src.display())
+            }
+            LinkStatus::Decrypted(src, dst) => {
+                write!(f, "Decrypted: {} (from {})", dst.display(), src.display())
+            }
+            LinkStatus::Copied(src, dst) => {
+                write!(f, "Copied: {} (from {})", dst.display(), src.display())
+            }
+            LinkStatus::Edited(src, dst) => write!(
+                f,
+                "Edited: {} (changed in place since it was copied from {})",
+                dst.display(),
+                src.display()
+            ),
+            LinkStatus::Outdated(src, dst) => write!(
+                f,
+                "Outdated: {} (differs from what {} produces)",
+                dst.display(),
+                src.display()
+            ),
+            LinkStatus::WrongMode(path, expected, actual) => write!(
+                f,
+                "Wrong mode: {} is {:o} (expected {:o})",
+                path.display(),
+                actual,
+                expected
+            ),
+            LinkStatus::WrongOwner(path, expected, actual) => write!(
+                f,
+                "Wrong owner: {} is owned by {} (expected {})",
+                path.display(),
+                actual,
+                expected
+            ),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::constants::{DEFAULT_CONFIG_TOML, TEST_HOME_DIR};
+use crate::error::AppError;
+use crate::models::link::FileProcessResult;
+use clap::{Args, ValueHint};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct AddArgs {
+    #[arg(
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        help = "The files or directories to move into the dotfiles directory",
+        value_name = "PATH"
+    )]
+    paths: Vec<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::DirPath,
+        help = "The dotfiles directory to add the files to",
+        value_name = "SOURCE_DOTFILES_DIR_PATH"
+    )]
+    repo: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Add from the test directory instead of the home directory for testing purposes"
+    )]
+    test: bool,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::FilePath,
+        default_value = DEFAULT_CONFIG_TOML,
+        help = "The TOML file whose [[link]] entry for the dotfiles directory decides which names are refused",
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        env = "WIDOTS_PROFILE",
+        help = "The profile in the TOML file to take the [[link]] entry from, by default the one listing this host",
+        value_name = "PROFILE"
+    )]
+    profile: Option<String>,
+}
+
+pub async fn execute(args: AddArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+    let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+    let target = if args.test {
+        home.join(TEST_HOME_DIR)
+    } else {
+        home
+    };
+
+    // Refuse what linking with the [[link]] of the dotfiles directory would skip
+    let (target, options) = services
+        .link_service()
+        .configured_link(&args.config, args.profile.as_deref(), &args.repo, &target)
+        .await?;
+    let results = services
+        .link_service()
+        .add_dotfiles(&args.repo, &target, &args.paths, &options)
+        .await?;
+
+    for result in results {
+        match result {
+            FileProcessResult::Added(path, src) => {
+                println!("Added: {} -> {}", path.display(), src.display());
+            }
+            FileProcessResult::Linked(src, dst) => {
+                println!("Linked: {} -> {}", src.display(), dst.display());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::services::brew_service::BrewService;
+    use crate::application::services::deploy_service::DeployService;
+    use crate::application::services::fish_service::FishService;
+    use crate::application::services::link_service::LinkService;
+    use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
+    use crate::application::services::vscode_service::VSCodeService;
+    use crate::models::diff::FileDiff;
+    use crate::models::link::LinkOptions;
+    use crate::models::plan::LinkPlan;
+    use crate::models::status::LinkStatus;
+    use async_trait::async_trait;
+    use mockall::predicate::*;
+    use mockall::*;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    mock! {
+        pub ServiceProvider {}
+        impl ServiceProvider for ServiceProvider {
+            fn brew_service(&self) -> Arc<dyn BrewService>;
+            fn link_service(&self) -> Arc<dyn LinkService>;
+            fn load_service(&self) -> Arc<dyn LoadService>;
+            fn deploy_service(&self) -> Arc<dyn DeployService>;
+            fn fish_service(&self) -> Arc<dyn FishService>;
+            fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
+        }
+    }
+
+    mock! {
+        pub LinkService {}
+        #[async_trait]
+        impl LinkService for LinkService {
+            async fn link_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+            async fn status_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions, paths: &[PathBuf]) -> Result<Vec<FileDiff>, AppError>;
+            async fn materialize_dotfiles(&self, target: &Path, source: Option<PathBuf>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn restore_backup(&self, run_id: Option<String>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn list_backups(&self) -> Result<Vec<String>, AppError>;
+            async fn add_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn watch_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<(), AppError>;
+            async fn config_vars(&self, config_path: &Path) -> Result<std::collections::BTreeMap<String, String>, AppError>;
+            async fn configured_link<'a>(&self, config_path: &'a Path, profile: Option<&'a str>, source: &'a Path, target: &'a Path) -> Result<(PathBuf, LinkOptions), AppError>;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_add() {
+        let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_configured_link()
+            .withf(|config, profile, source, _| {
+                config == Path::new("/config.toml")
+                    && profile.is_none()
+                    && source == Path::new("/src")
+            })
+            .returning(|_, _, _, target| {
+                Ok((
+                    target.to_path_buf(),
+                    LinkOptions {
+                        deny_chars: vec!['#'],
+                        ..Default::default()
+                    },
+                ))
+            });
+        mock_link_service
+            .expect_add_dotfiles()
+            .withf(|source, _, paths, options| {
+                source == Path::new("/src")
+                    && paths == [PathBuf::from("~/.vimrc")]
+                    && options.deny_chars == ['#']
+            })
+            .times(1)
+            .returning(|source, target, _, _| {
+                Ok(vec![
+                    FileProcessResult::Added(target.join(".vimrc"), source.join(".vimrc")),
+                    FileProcessResult::Linked(source.join(".vimrc"), target.join(".vimrc")),
+                ])
+            });
+
+        let mut mock_service_provider = MockServiceProvider::new();
+        mock_service_provider
+            .expect_link_service()
+            .return_const(Arc::new(mock_link_service) as Arc<dyn LinkService>);
+
+        let args = AddArgs {
+            paths: vec![PathBuf::from("~/.vimrc")],
+            repo: PathBuf::from("/src"),
+            test: true,
+            config: PathBuf::from("/config.toml"),
+            profile: None,
+        };
+
+        let result = execute(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+}
@@ -1,6 +1,8 @@
 use crate::application::service_provider::ServiceProvider;
+use crate::constants::DEFAULT_CONFIG_TOML;
 use crate::error::AppError;
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueHint};
+use std::path::PathBuf;
 
 #[derive(Args)]
 pub struct BrewArgs {
@@ -11,18 +13,41 @@ pub struct BrewArgs {
 #[derive(Subcommand)]
 enum BrewCommands {
     Install,
-    Import,
+    Import(ImportArgs),
     Export,
 }
 
+#[derive(Args)]
+struct ImportArgs {
+    #[arg(
+        value_hint = ValueHint::FilePath,
+        help = "The path to the TOML file whose [[package]] formulas and casks to import as well",
+        default_value = DEFAULT_CONFIG_TOML,
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config_toml: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        env = "WIDOTS_PROFILE",
+        help = "The profile in the TOML file whose packages to import, by default the one listing this host",
+        value_name = "PROFILE"
+    )]
+    profile: Option<String>,
+}
+
 pub async fn execute(args: BrewArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
     match args.command {
         BrewCommands::Install => {
             services.brew_service().install().await?;
             println!("Homebrew installed successfully");
         }
-        BrewCommands::Import => {
-            services.brew_service().import().await?;
+        BrewCommands::Import(import_args) => {
+            services
+                .brew_service()
+                .import(&import_args.config_toml, import_args.profile.as_deref())
+                .await?;
             println!("Homebrew packages imported successfully");
         }
         BrewCommands::Export => {
@@ -41,12 +66,16 @@ mod tests {
     use crate::application::services::fish_service::FishService;
     use crate::application::services::link_service::LinkService;
     use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
     use crate::application::services::vscode_service::VSCodeService;
     use crate::error::AppError;
-    use crate::models::link::FileProcessResult;
-    use crate::presentation::cli::commands::brew::{execute, BrewArgs, BrewCommands};
+    use crate::models::diff::FileDiff;
+    use crate::models::link::{FileProcessResult, LinkOptions};
+    use crate::models::plan::{LinkPlan, LoadPlan};
+    use crate::models::status::LinkStatus;
+    use crate::presentation::cli::commands::brew::{execute, BrewArgs, BrewCommands, ImportArgs};
     use async_trait::async_trait;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
     use std::sync::Arc;
 
     struct CustomMockBrewService;
@@ -57,7 +86,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import(&self) -> Result<(), AppError> {
+        async fn import<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -74,13 +107,134 @@ mod tests {
             &self,
             _source: &Path,
             _target: &Path,
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
 
+        async fn plan_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
         async fn materialize_dotfiles(
             &self,
             _target: &Path,
+            _source: Option<PathBuf>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn restore_backup(
+            &self,
+            _run_id: Option<String>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn list_backups(&self) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn status_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn diff_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+            _paths: &[PathBuf],
+        ) -> Result<Vec<FileDiff>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn add_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn watch_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn config_vars(
+            &self,
+            _config_path: &Path,
+        ) -> Result<std::collections::BTreeMap<String, String>, AppError> {
+            Ok(std::collections::BTreeMap::new())
+        }
+
+        async fn configured_link<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+            _source: &'a Path,
+            target: &'a Path,
+        ) -> Result<(PathBuf, LinkOptions), AppError> {
+            Ok((target.to_path_buf(), LinkOptions::default()))
+        }
+
+        async fn encrypt_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
@@ -90,9 +244,32 @@ mod tests {
 
     #[async_trait]
     impl LoadService for CustomMockLoadService {
-        async fn load(&self, _config_path: &Path, _target: &Path) -> Result<(), AppError> {
+        async fn load<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
+
+        async fn plan<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<LoadPlan, AppError> {
+            Ok(LoadPlan::default())
+        }
+
+        async fn check_permissions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
     }
 
     struct CustomMockDeployService;
@@ -129,7 +306,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import_extensions(&self) -> Result<(), AppError> {
+        async fn import_extensions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -138,6 +319,31 @@ mod tests {
         }
     }
 
+    struct CustomMockSyncService;
+
+    #[async_trait]
+    impl SyncService for CustomMockSyncService {
+        async fn repo_of(&self, _config_path: &Path) -> Result<PathBuf, AppError> {
+            Ok(PathBuf::new())
+        }
+
+        async fn pull(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn changes(&self, _repo: &Path) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn commit(&self, _repo: &Path, _changes: &[String]) -> Result<String, AppError> {
+            Ok(String::new())
+        }
+
+        async fn push(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
     struct CustomMockServiceProvider {
         brew_service: Arc<dyn BrewService>,
         link_service: Arc<dyn LinkService>,
@@ -145,6 +351,7 @@ mod tests {
         deploy_service: Arc<dyn DeployService>,
         fish_service: Arc<dyn FishService>,
         vscode_service: Arc<dyn VSCodeService>,
+        sync_service: Arc<dyn SyncService>,
     }
 
     impl CustomMockServiceProvider {
@@ -156,6 +363,7 @@ mod tests {
                 deploy_service: Arc::new(CustomMockDeployService) as Arc<dyn DeployService>,
                 fish_service: Arc::new(CustomMockFishService) as Arc<dyn FishService>,
                 vscode_service: Arc::new(CustomMockVSCodeService) as Arc<dyn VSCodeService>,
+                sync_service: Arc::new(CustomMockSyncService) as Arc<dyn SyncService>,
             }
         }
     }
@@ -184,6 +392,10 @@ mod tests {
         fn vscode_service(&self) -> Arc<dyn VSCodeService> {
             Arc::clone(&self.vscode_service)
         }
+
+        fn sync_service(&self) -> Arc<dyn SyncService> {
+            Arc::clone(&self.sync_service)
+        }
     }
 
     #[tokio::test]
@@ -202,7 +414,10 @@ mod tests {
         let mock_services = Arc::new(CustomMockServiceProvider::new()) as Arc<dyn ServiceProvider>;
 
         let args = BrewArgs {
-            command: BrewCommands::Import,
+            command: BrewCommands::Import(ImportArgs {
+                config_toml: PathBuf::new(),
+                profile: Some("work".to_string()),
+            }),
         };
         let result = execute(args, mock_services.as_ref()).await;
         assert!(result.is_ok());
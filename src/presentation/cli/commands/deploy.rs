@@ -15,12 +15,16 @@ mod tests {
     use crate::application::services::fish_service::FishService;
     use crate::application::services::link_service::LinkService;
     use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
     use crate::application::services::vscode_service::VSCodeService;
     use crate::error::AppError;
-    use crate::models::link::FileProcessResult;
+    use crate::models::diff::FileDiff;
+    use crate::models::link::{FileProcessResult, LinkOptions};
+    use crate::models::plan::{LinkPlan, LoadPlan};
+    use crate::models::status::LinkStatus;
     use crate::presentation::cli::commands::deploy::execute;
     use async_trait::async_trait;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
     use std::sync::Arc;
 
     struct CustomMockBrewService;
@@ -31,7 +35,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import(&self) -> Result<(), AppError> {
+        async fn import<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -48,13 +56,134 @@ mod tests {
             &self,
             _source: &Path,
             _target: &Path,
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
 
+        async fn plan_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
         async fn materialize_dotfiles(
             &self,
             _target: &Path,
+            _source: Option<PathBuf>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn restore_backup(
+            &self,
+            _run_id: Option<String>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn list_backups(&self) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn status_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn diff_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+            _paths: &[PathBuf],
+        ) -> Result<Vec<FileDiff>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn add_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn watch_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn config_vars(
+            &self,
+            _config_path: &Path,
+        ) -> Result<std::collections::BTreeMap<String, String>, AppError> {
+            Ok(std::collections::BTreeMap::new())
+        }
+
+        async fn configured_link<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+            _source: &'a Path,
+            target: &'a Path,
+        ) -> Result<(PathBuf, LinkOptions), AppError> {
+            Ok((target.to_path_buf(), LinkOptions::default()))
+        }
+
+        async fn encrypt_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
@@ -64,9 +193,32 @@ mod tests {
 
     #[async_trait]
     impl LoadService for CustomMockLoadService {
-        async fn load(&self, _config_path: &Path, _target: &Path) -> Result<(), AppError> {
+        async fn load<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
+
+        async fn plan<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<LoadPlan, AppError> {
+            Ok(LoadPlan::default())
+        }
+
+        async fn check_permissions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
     }
 
     struct CustomMockDeployService;
@@ -103,7 +255,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import_extensions(&self) -> Result<(), AppError> {
+        async fn import_extensions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -112,6 +268,31 @@ mod tests {
         }
     }
 
+    struct CustomMockSyncService;
+
+    #[async_trait]
+    impl SyncService for CustomMockSyncService {
+        async fn repo_of(&self, _config_path: &Path) -> Result<PathBuf, AppError> {
+            Ok(PathBuf::new())
+        }
+
+        async fn pull(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn changes(&self, _repo: &Path) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn commit(&self, _repo: &Path, _changes: &[String]) -> Result<String, AppError> {
+            Ok(String::new())
+        }
+
+        async fn push(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
     struct CustomMockServiceProvider {
         brew_service: Arc<dyn BrewService>,
         link_service: Arc<dyn LinkService>,
@@ -119,6 +300,7 @@ mod tests {
         deploy_service: Arc<dyn DeployService>,
         fish_service: Arc<dyn FishService>,
         vscode_service: Arc<dyn VSCodeService>,
+        sync_service: Arc<dyn SyncService>,
     }
 
     impl CustomMockServiceProvider {
@@ -130,6 +312,7 @@ mod tests {
                 deploy_service: Arc::new(CustomMockDeployService) as Arc<dyn DeployService>,
                 fish_service: Arc::new(CustomMockFishService) as Arc<dyn FishService>,
                 vscode_service: Arc::new(CustomMockVSCodeService) as Arc<dyn VSCodeService>,
+                sync_service: Arc::new(CustomMockSyncService) as Arc<dyn SyncService>,
             }
         }
     }
@@ -158,6 +341,10 @@ mod tests {
         fn vscode_service(&self) -> Arc<dyn VSCodeService> {
             Arc::clone(&self.vscode_service)
         }
+
+        fn sync_service(&self) -> Arc<dyn SyncService> {
+            Arc::clone(&self.sync_service)
+        }
     }
 
     #[tokio::test]
@@ -0,0 +1,345 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::constants::{DEFAULT_CONFIG_TOML, TEST_HOME_DIR};
+use crate::error::AppError;
+use crate::models::diff::FileDiff;
+use crate::models::link::LinkOptions;
+use clap::{Args, ValueHint};
+use colored::Colorize;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    #[arg(
+        value_hint = ValueHint::AnyPath,
+        help = "Only diff the managed files in these paths, in the target or the dotfiles directory",
+        value_name = "PATH"
+    )]
+    paths: Vec<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::DirPath,
+        help = "The dotfiles directory the files are deployed from",
+        value_name = "SOURCE_DOTFILES_DIR_PATH"
+    )]
+    repo: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Diff against the test directory instead of the home directory for testing purposes"
+    )]
+    test: bool,
+
+    #[arg(long, help = "Only summarize how many lines changed in each file")]
+    stat: bool,
+
+    #[arg(
+        long,
+        help = "Also skip files excluded by .gitignore files in the dotfiles directory"
+    )]
+    gitignore: bool,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::FilePath,
+        default_value = DEFAULT_CONFIG_TOML,
+        help = "The TOML file whose [[link]] entry for the dotfiles directory and [vars] to diff with",
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        env = "WIDOTS_PROFILE",
+        help = "The profile in the TOML file to diff with, by default the one listing this host",
+        value_name = "PROFILE"
+    )]
+    profile: Option<String>,
+}
+
+pub async fn execute(args: DiffArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+    let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+    let target = if args.test {
+        home.join(TEST_HOME_DIR)
+    } else {
+        home
+    };
+    // Diff where and how the [[link]] of the dotfiles directory links, as load would
+    let (target, options) = services
+        .link_service()
+        .configured_link(&args.config, args.profile.as_deref(), &args.repo, &target)
+        .await?;
+    let options = LinkOptions {
+        gitignore: args.gitignore || options.gitignore,
+        ..options
+    };
+
+    let diffs = services
+        .link_service()
+        .diff_dotfiles(&args.repo, &target, &options, &args.paths)
+        .await?;
+
+    if diffs.is_empty() {
+        println!("No differences");
+        return Ok(());
+    }
+
+    if args.stat {
+        print_stat(&diffs);
+        return Ok(());
+    }
+    for diff in &diffs {
+        match unified_diff(diff) {
+            Some(lines) => {
+                for line in lines {
+                    println!("{}", colorize(&line));
+                }
+            }
+            None => println!(
+                "Binary files {} and {} differ",
+                diff.source.display(),
+                diff.target.display()
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the lines of a unified diff from the source to the target, or `None` if either is
+/// not text.
+fn unified_diff(diff: &FileDiff) -> Option<Vec<String>> {
+    let expected = std::str::from_utf8(&diff.expected).ok()?;
+    let actual = std::str::from_utf8(&diff.actual).ok()?;
+    let expected: Vec<&str> = expected.split_inclusive('\n').collect();
+    let actual: Vec<&str> = actual.split_inclusive('\n').collect();
+
+    let lines = difflib::unified_diff(
+        &expected,
+        &actual,
+        &diff.source.to_string_lossy(),
+        &diff.target.to_string_lossy(),
+        "",
+        "",
+        3,
+    );
+    // Only the line terminators go, so that changes in trailing whitespace still show
+    Some(
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let line = line.strip_suffix('\n').unwrap_or(line);
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                // The file names are followed by a tab for dates, which are left out
+                match i {
+                    0 | 1 => line.trim_end_matches('\t').to_string(),
+                    _ => line.to_string(),
+                }
+            })
+            .collect(),
+    )
+}
+
+fn colorize(line: &str) -> String {
+    if line.starts_with("---") || line.starts_with("+++") {
+        line.bold().to_string()
+    } else if line.starts_with("@@") {
+        line.cyan().to_string()
+    } else if line.starts_with('-') {
+        line.red().to_string()
+    } else if line.starts_with('+') {
+        line.green().to_string()
+    } else {
+        line.to_string()
+    }
+}
+
+/// Counts the lines a diff inserts and deletes, or returns `None` for binary files.
+fn count_changes(diff: &FileDiff) -> Option<(usize, usize)> {
+    let lines = unified_diff(diff)?;
+    // The first two lines name the files
+    let changes = lines.iter().skip(2);
+    let insertions = changes.clone().filter(|line| line.starts_with('+')).count();
+    let deletions = changes.filter(|line| line.starts_with('-')).count();
+    Some((insertions, deletions))
+}
+
+fn print_stat(diffs: &[FileDiff]) {
+    let (mut insertions, mut deletions) = (0, 0);
+    for diff in diffs {
+        match count_changes(diff) {
+            Some((inserted, deleted)) => {
+                println!(
+                    " {} | {} {}{}",
+                    diff.target.display(),
+                    inserted + deleted,
+                    "+".repeat(inserted).green(),
+                    "-".repeat(deleted).red()
+                );
+                insertions += inserted;
+                deletions += deleted;
+            }
+            None => println!(" {} | Bin", diff.target.display()),
+        }
+    }
+    println!(
+        " {} files changed, {} insertions(+), {} deletions(-)",
+        diffs.len(),
+        insertions,
+        deletions
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::services::brew_service::BrewService;
+    use crate::application::services::deploy_service::DeployService;
+    use crate::application::services::fish_service::FishService;
+    use crate::application::services::link_service::LinkService;
+    use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
+    use crate::application::services::vscode_service::VSCodeService;
+    use crate::models::link::FileProcessResult;
+    use crate::models::plan::LinkPlan;
+    use crate::models::status::LinkStatus;
+    use async_trait::async_trait;
+    use mockall::predicate::*;
+    use mockall::*;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    mock! {
+        pub ServiceProvider {}
+        impl ServiceProvider for ServiceProvider {
+            fn brew_service(&self) -> Arc<dyn BrewService>;
+            fn link_service(&self) -> Arc<dyn LinkService>;
+            fn load_service(&self) -> Arc<dyn LoadService>;
+            fn deploy_service(&self) -> Arc<dyn DeployService>;
+            fn fish_service(&self) -> Arc<dyn FishService>;
+            fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
+        }
+    }
+
+    mock! {
+        pub LinkService {}
+        #[async_trait]
+        impl LinkService for LinkService {
+            async fn link_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+            async fn status_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions, paths: &[PathBuf]) -> Result<Vec<FileDiff>, AppError>;
+            async fn materialize_dotfiles(&self, target: &Path, source: Option<PathBuf>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn restore_backup(&self, run_id: Option<String>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn list_backups(&self) -> Result<Vec<String>, AppError>;
+            async fn add_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn watch_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<(), AppError>;
+            async fn config_vars(&self, config_path: &Path) -> Result<std::collections::BTreeMap<String, String>, AppError>;
+            async fn configured_link<'a>(&self, config_path: &'a Path, profile: Option<&'a str>, source: &'a Path, target: &'a Path) -> Result<(PathBuf, LinkOptions), AppError>;
+        }
+    }
+
+    fn vimrc_diff() -> FileDiff {
+        FileDiff {
+            source: PathBuf::from("/src/.vimrc"),
+            target: PathBuf::from("/dst/.vimrc"),
+            expected: b"syntax on\nset number\n".to_vec(),
+            actual: b"syntax on\nset nonumber\nset hlsearch\n".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(
+            unified_diff(&vimrc_diff()),
+            Some(vec![
+                "--- /src/.vimrc".to_string(),
+                "+++ /dst/.vimrc".to_string(),
+                "@@ -1,2 +1,3 @@".to_string(),
+                " syntax on".to_string(),
+                "-set number".to_string(),
+                "+set nonumber".to_string(),
+                "+set hlsearch".to_string(),
+            ])
+        );
+        assert_eq!(count_changes(&vimrc_diff()), Some((2, 1)));
+
+        let whitespace = FileDiff {
+            expected: b"set number\r\n".to_vec(),
+            actual: b"set number \r\n".to_vec(),
+            ..vimrc_diff()
+        };
+        assert_eq!(
+            unified_diff(&whitespace).unwrap()[3..],
+            ["-set number", "+set number "]
+        );
+
+        let binary = FileDiff {
+            actual: vec![0xff, 0xfe],
+            ..vimrc_diff()
+        };
+        assert_eq!(unified_diff(&binary), None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_diff() {
+        let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_configured_link()
+            .withf(|config, profile, source, _| {
+                config == Path::new("/config.toml")
+                    && *profile == Some("work")
+                    && source == Path::new("/src")
+            })
+            .returning(|_, _, _, target| {
+                Ok((
+                    target.join(".config"),
+                    LinkOptions {
+                        include: vec!["nvim/**".to_string()],
+                        ..Default::default()
+                    },
+                ))
+            });
+        mock_link_service
+            .expect_diff_dotfiles()
+            .withf(|source, target, options, paths| {
+                source == Path::new("/src")
+                    && target.ends_with(".config")
+                    && options.include == vec!["nvim/**"]
+                    && options.gitignore
+                    && paths == [PathBuf::from("~/.vimrc")]
+            })
+            .times(2)
+            .returning(|_, _, _, _| Ok(vec![vimrc_diff()]));
+
+        let mut mock_service_provider = MockServiceProvider::new();
+        mock_service_provider
+            .expect_link_service()
+            .return_const(Arc::new(mock_link_service) as Arc<dyn LinkService>);
+
+        for stat in [false, true] {
+            let args = DiffArgs {
+                paths: vec![PathBuf::from("~/.vimrc")],
+                repo: PathBuf::from("/src"),
+                test: true,
+                stat,
+                gitignore: true,
+                config: PathBuf::from("/config.toml"),
+                profile: Some("work".to_string()),
+            };
+            let result = execute(args, &mock_service_provider).await;
+            assert!(result.is_ok());
+        }
+    }
+}
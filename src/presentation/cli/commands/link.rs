@@ -1,7 +1,7 @@
 use crate::application::service_provider::ServiceProvider;
-use crate::constants::TEST_HOME_DIR;
+use crate::constants::{DEFAULT_CONFIG_TOML, TEST_HOME_DIR};
 use crate::error::AppError;
-use crate::models::link::FileProcessResult;
+use crate::models::link::{ConflictStrategy, FileProcessResult, LinkMethod, LinkOptions};
 use clap::{Args, ValueHint};
 use std::path::PathBuf;
 
@@ -20,19 +20,98 @@ pub struct LinkArgs {
         help = "Link to the test directory instead of the home directory for testing purposes"
     )]
     test: bool,
+
+    #[arg(
+        long,
+        help = "Also skip files excluded by .gitignore files in the dotfiles directory"
+    )]
+    gitignore: bool,
+
+    #[arg(
+        long,
+        help = "Link whole directories that do not exist in the target yet"
+    )]
+    fold: bool,
+
+    #[arg(long, help = "Create links with paths relative to their directory")]
+    relative: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ConflictStrategy::Backup,
+        help = "What to do when a target file already exists"
+    )]
+    on_conflict: ConflictStrategy,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LinkMethod::Symlink,
+        help = "How to place each file in the target"
+    )]
+    method: LinkMethod,
+
+    #[arg(
+        long,
+        default_value = "",
+        help = "Skip files whose names contain any of these characters",
+        value_name = "CHARS"
+    )]
+    deny_chars: String,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::FilePath,
+        default_value = DEFAULT_CONFIG_TOML,
+        help = "The TOML file whose [vars] templates are rendered with",
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config: PathBuf,
 }
 
-pub async fn execute(args: LinkArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+pub async fn execute(
+    args: LinkArgs,
+    dry_run: bool,
+    services: &dyn ServiceProvider,
+) -> Result<(), AppError> {
     let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
     let target = if args.test {
         home.join(TEST_HOME_DIR)
     } else {
         home
     };
+    let options = LinkOptions {
+        gitignore: args.gitignore,
+        fold: args.fold,
+        relative: args.relative,
+        on_conflict: args.on_conflict,
+        method: args.method,
+        deny_chars: args.deny_chars.chars().collect(),
+        vars: services.link_service().config_vars(&args.config).await?,
+        ..Default::default()
+    };
+
+    if dry_run {
+        let plan = services
+            .link_service()
+            .plan_dotfiles(&args.source_path, &target, &options)
+            .await?;
+        println!(
+            "Dry run: linking {} -> {}",
+            plan.source.display(),
+            plan.target.display()
+        );
+        for action in &plan.actions {
+            println!("  {}", action);
+        }
+        return Ok(());
+    }
 
     let results = services
         .link_service()
-        .link_dotfiles(&args.source_path, &target)
+        .link_dotfiles(&args.source_path, &target, &options)
         .await?;
 
     for result in results {
@@ -43,10 +122,43 @@ pub async fn execute(args: LinkArgs, services: &dyn ServiceProvider) -> Result<(
             FileProcessResult::Created(path) => {
                 println!("Created directory: {}", path.display());
             }
+            FileProcessResult::Rendered(src, dst) => {
+                println!("Rendered: {} -> {}", src.display(), dst.display());
+            }
+            FileProcessResult::Decrypted(src, dst) => {
+                println!("Decrypted: {} -> {}", src.display(), dst.display());
+            }
+            FileProcessResult::Copied(src, dst) => {
+                println!("Copied: {} -> {}", src.display(), dst.display());
+            }
             FileProcessResult::Skipped(path) => {
                 println!("Skipped: {}", path.display());
             }
-            FileProcessResult::Materialized(_, _) => {} // This should not occur during linking
+            FileProcessResult::BackedUp(path, backup) => {
+                println!("Backed up: {} -> {}", path.display(), backup.display());
+            }
+            FileProcessResult::Conflicted(path, strategy) => {
+                println!("Conflict: {} (resolved with {})", path.display(), strategy);
+            }
+            // Unfolded directories and stale links that were pruned
+            FileProcessResult::Unlinked(path, src) => {
+                println!(
+                    "Unlinked: {} (was linked to {})",
+                    path.display(),
+                    src.display()
+                );
+            }
+            FileProcessResult::Removed(path) => {
+                println!("Removed: {}", path.display());
+            }
+            // These should not occur during linking
+            FileProcessResult::Materialized(_, _)
+            | FileProcessResult::Restored(_, _)
+            | FileProcessResult::Added(_, _)
+            | FileProcessResult::Encrypted(_, _)
+            | FileProcessResult::ModeChanged(_, _)
+            | FileProcessResult::OwnerChanged(_, _)
+            | FileProcessResult::Failed(_, _) => {}
         }
     }
 
@@ -61,7 +173,11 @@ mod tests {
     use crate::application::services::fish_service::FishService;
     use crate::application::services::link_service::LinkService;
     use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
     use crate::application::services::vscode_service::VSCodeService;
+    use crate::models::diff::FileDiff;
+    use crate::models::plan::{LinkAction, LinkPlan};
+    use crate::models::status::LinkStatus;
     use async_trait::async_trait;
     use mockall::predicate::*;
     use mockall::*;
@@ -78,6 +194,7 @@ mod tests {
             fn deploy_service(&self) -> Arc<dyn DeployService>;
             fn fish_service(&self) -> Arc<dyn FishService>;
             fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
         }
     }
 
@@ -85,8 +202,22 @@ mod tests {
         pub LinkService {}
         #[async_trait]
         impl LinkService for LinkService {
-            async fn link_dotfiles(&self, source: &Path, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
-            async fn materialize_dotfiles(&self, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn link_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn status_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions, paths: &[PathBuf]) -> Result<Vec<FileDiff>, AppError>;
+            async fn materialize_dotfiles(&self, target: &Path, source: Option<PathBuf>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn restore_backup(&self, run_id: Option<String>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn list_backups(&self) -> Result<Vec<String>, AppError>;
+            async fn add_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn watch_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<(), AppError>;
+            async fn config_vars(&self, config_path: &Path) -> Result<std::collections::BTreeMap<String, String>, AppError>;
+            async fn configured_link<'a>(&self, config_path: &'a Path, profile: Option<&'a str>, source: &'a Path, target: &'a Path) -> Result<(PathBuf, LinkOptions), AppError>;
+            async fn unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
         }
     }
 
@@ -97,10 +228,19 @@ mod tests {
         std::fs::create_dir(&source_path).unwrap();
 
         let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_config_vars()
+            .with(eq(PathBuf::from("/config.toml")))
+            .returning(|_| Ok([("email".to_string(), "alice@example.com".to_string())].into()));
+        // Templates are rendered with the [vars] of the config
+        let expected_source = source_path.clone();
         mock_link_service
             .expect_link_dotfiles()
-            .with(eq(source_path.clone()), always())
-            .returning(|_, _| {
+            .withf(move |source, _, options| {
+                source == expected_source && options.vars["email"] == "alice@example.com"
+            })
+            .times(1)
+            .returning(|_, _, _| {
                 Ok(vec![
                     FileProcessResult::Linked(
                         PathBuf::from("/src/file1"),
@@ -123,9 +263,16 @@ mod tests {
         let args = LinkArgs {
             source_path: source_path.clone(),
             test: false,
+            gitignore: false,
+            fold: false,
+            relative: false,
+            on_conflict: ConflictStrategy::Backup,
+            method: LinkMethod::Symlink,
+            deny_chars: String::new(),
+            config: PathBuf::from("/config.toml"),
         };
 
-        let result = execute(args, &mock_service_provider).await;
+        let result = execute(args, false, &mock_service_provider).await;
         assert!(result.is_ok());
     }
 
@@ -136,10 +283,13 @@ mod tests {
         std::fs::create_dir(&source_path).unwrap();
 
         let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_config_vars()
+            .returning(|_| Ok(Default::default()));
         mock_link_service
             .expect_link_dotfiles()
-            .with(eq(source_path.clone()), always())
-            .returning(|_, _| {
+            .with(eq(source_path.clone()), always(), always())
+            .returning(|_, _, _| {
                 Ok(vec![
                     FileProcessResult::Linked(
                         PathBuf::from("/src/file1"),
@@ -158,9 +308,61 @@ mod tests {
         let args = LinkArgs {
             source_path: source_path.clone(),
             test: true,
+            gitignore: false,
+            fold: false,
+            relative: false,
+            on_conflict: ConflictStrategy::Backup,
+            method: LinkMethod::Symlink,
+            deny_chars: String::new(),
+            config: PathBuf::from("/config.toml"),
+        };
+
+        let result = execute(args, false, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_execute_link_dotfiles_dry_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let source_path = temp_dir.path().join("source");
+        std::fs::create_dir(&source_path).unwrap();
+
+        let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_config_vars()
+            .returning(|_| Ok(Default::default()));
+        mock_link_service.expect_link_dotfiles().times(0);
+        mock_link_service
+            .expect_plan_dotfiles()
+            .with(eq(source_path.clone()), always(), always())
+            .times(1)
+            .returning(|source, target, _| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![LinkAction::Link(source.join("file1"), target.join("file1"))],
+                    ..Default::default()
+                })
+            });
+
+        let mut mock_service_provider = MockServiceProvider::new();
+        mock_service_provider
+            .expect_link_service()
+            .return_const(Arc::new(mock_link_service) as Arc<dyn LinkService>);
+
+        let args = LinkArgs {
+            source_path: source_path.clone(),
+            test: true,
+            gitignore: false,
+            fold: false,
+            relative: false,
+            on_conflict: ConflictStrategy::Backup,
+            method: LinkMethod::Symlink,
+            deny_chars: String::new(),
+            config: PathBuf::from("/config.toml"),
         };
 
-        let result = execute(args, &mock_service_provider).await;
+        let result = execute(args, true, &mock_service_provider).await;
         assert!(result.is_ok());
     }
 }
@@ -21,9 +21,22 @@ pub struct LoadArgs {
         help = "Link to the test directory instead of the home directory for testing purposes"
     )]
     test: bool,
+
+    #[arg(
+        short,
+        long,
+        env = "WIDOTS_PROFILE",
+        help = "The profile in the TOML file whose links and provisions to apply, by default the one listing this host",
+        value_name = "PROFILE"
+    )]
+    profile: Option<String>,
 }
 
-pub async fn execute(args: LoadArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+pub async fn execute(
+    args: LoadArgs,
+    dry_run: bool,
+    services: &dyn ServiceProvider,
+) -> Result<(), AppError> {
     let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
     let target = if args.test {
         home.join(TEST_HOME_DIR)
@@ -31,9 +44,48 @@ pub async fn execute(args: LoadArgs, services: &dyn ServiceProvider) -> Result<(
         home
     };
 
+    if dry_run {
+        let plan = services
+            .load_service()
+            .plan(&args.config_toml, &target, args.profile.as_deref())
+            .await?;
+        if let Some(profile) = &plan.profile {
+            println!("Dry run: using profile {}", profile);
+        }
+        for link in &plan.links {
+            println!(
+                "Dry run: linking {} -> {}",
+                link.source.display(),
+                link.target.display()
+            );
+            for action in &link.actions {
+                println!("  {}", action);
+            }
+        }
+        if !plan.stale.actions.is_empty() {
+            println!("Dry run: pruning sources that are no longer configured");
+            for action in &plan.stale.actions {
+                println!("  {}", action);
+            }
+        }
+        if !plan.permissions.is_empty() {
+            println!("Dry run: fixing permissions");
+            for status in &plan.permissions {
+                println!("  {}", status);
+            }
+        }
+        for provision in &plan.provisions {
+            println!("Dry run: provisioning for {}", provision.mode);
+            for line in provision.script.lines() {
+                println!("  {}", line);
+            }
+        }
+        return Ok(());
+    }
+
     services
         .load_service()
-        .load(&args.config_toml, &target)
+        .load(&args.config_toml, &target, args.profile.as_deref())
         .await
 }
 
@@ -45,9 +97,13 @@ mod tests {
     use crate::application::services::fish_service::FishService;
     use crate::application::services::link_service::LinkService;
     use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
     use crate::application::services::vscode_service::VSCodeService;
     use crate::error::AppError;
-    use crate::models::link::FileProcessResult;
+    use crate::models::diff::FileDiff;
+    use crate::models::link::{FileProcessResult, LinkOptions};
+    use crate::models::plan::{LinkPlan, LoadPlan};
+    use crate::models::status::LinkStatus;
     use crate::presentation::cli::commands::load::{execute, LoadArgs};
     use async_trait::async_trait;
     use std::path::{Path, PathBuf};
@@ -61,7 +117,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import(&self) -> Result<(), AppError> {
+        async fn import<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -78,13 +138,134 @@ mod tests {
             &self,
             _source: &Path,
             _target: &Path,
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
 
+        async fn plan_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
         async fn materialize_dotfiles(
             &self,
             _target: &Path,
+            _source: Option<PathBuf>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn restore_backup(
+            &self,
+            _run_id: Option<String>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn list_backups(&self) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn status_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn diff_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+            _paths: &[PathBuf],
+        ) -> Result<Vec<FileDiff>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn add_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn watch_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn config_vars(
+            &self,
+            _config_path: &Path,
+        ) -> Result<std::collections::BTreeMap<String, String>, AppError> {
+            Ok(std::collections::BTreeMap::new())
+        }
+
+        async fn configured_link<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+            _source: &'a Path,
+            target: &'a Path,
+        ) -> Result<(PathBuf, LinkOptions), AppError> {
+            Ok((target.to_path_buf(), LinkOptions::default()))
+        }
+
+        async fn encrypt_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
@@ -94,9 +275,32 @@ mod tests {
 
     #[async_trait]
     impl LoadService for CustomMockLoadService {
-        async fn load(&self, _config_path: &Path, _target: &Path) -> Result<(), AppError> {
+        async fn load<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
+
+        async fn plan<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<LoadPlan, AppError> {
+            Ok(LoadPlan::default())
+        }
+
+        async fn check_permissions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
     }
 
     struct CustomMockDeployService;
@@ -133,7 +337,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import_extensions(&self) -> Result<(), AppError> {
+        async fn import_extensions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -142,6 +350,31 @@ mod tests {
         }
     }
 
+    struct CustomMockSyncService;
+
+    #[async_trait]
+    impl SyncService for CustomMockSyncService {
+        async fn repo_of(&self, _config_path: &Path) -> Result<PathBuf, AppError> {
+            Ok(PathBuf::new())
+        }
+
+        async fn pull(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn changes(&self, _repo: &Path) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn commit(&self, _repo: &Path, _changes: &[String]) -> Result<String, AppError> {
+            Ok(String::new())
+        }
+
+        async fn push(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
     struct CustomMockServiceProvider {
         brew_service: Arc<dyn BrewService>,
         link_service: Arc<dyn LinkService>,
@@ -149,6 +382,7 @@ mod tests {
         deploy_service: Arc<dyn DeployService>,
         fish_service: Arc<dyn FishService>,
         vscode_service: Arc<dyn VSCodeService>,
+        sync_service: Arc<dyn SyncService>,
     }
 
     impl CustomMockServiceProvider {
@@ -160,6 +394,7 @@ mod tests {
                 deploy_service: Arc::new(CustomMockDeployService) as Arc<dyn DeployService>,
                 fish_service: Arc::new(CustomMockFishService) as Arc<dyn FishService>,
                 vscode_service: Arc::new(CustomMockVSCodeService) as Arc<dyn VSCodeService>,
+                sync_service: Arc::new(CustomMockSyncService) as Arc<dyn SyncService>,
             }
         }
     }
@@ -188,6 +423,10 @@ mod tests {
         fn vscode_service(&self) -> Arc<dyn VSCodeService> {
             Arc::clone(&self.vscode_service)
         }
+
+        fn sync_service(&self) -> Arc<dyn SyncService> {
+            Arc::clone(&self.sync_service)
+        }
     }
 
     #[tokio::test]
@@ -197,8 +436,9 @@ mod tests {
         let args = LoadArgs {
             config_toml: PathBuf::new(),
             test: false,
+            profile: None,
         };
-        let result = execute(args, mock_services.as_ref()).await;
+        let result = execute(args, false, mock_services.as_ref()).await;
         assert!(result.is_ok());
     }
 
@@ -209,8 +449,9 @@ mod tests {
         let args = LoadArgs {
             config_toml: PathBuf::new(),
             test: true,
+            profile: Some("work".to_string()),
         };
-        let result = execute(args, mock_services.as_ref()).await;
+        let result = execute(args, false, mock_services.as_ref()).await;
         assert!(result.is_ok());
     }
 }
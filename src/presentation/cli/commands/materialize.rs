@@ -15,6 +15,15 @@ pub struct MaterializeArgs {
         value_name = "TARGET_DOTFILES_DIR_PATH"
     )]
     target: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::DirPath,
+        help = "Only materialize links that point into this dotfiles directory",
+        value_name = "SOURCE_DOTFILES_DIR_PATH"
+    )]
+    source: Option<PathBuf>,
 }
 
 pub async fn execute(
@@ -23,24 +32,33 @@ pub async fn execute(
 ) -> Result<(), AppError> {
     let results = services
         .link_service()
-        .materialize_dotfiles(&args.target)
+        .materialize_dotfiles(&args.target, args.source)
         .await?;
 
+    let mut failed = 0;
     for result in results {
-        if let FileProcessResult::Materialized(path, original) = result {
-            let output = format!(
+        let output = match result {
+            FileProcessResult::Materialized(path, original) => format!(
                 "Materialized: {} (was linked to {})",
                 path.display(),
                 original.display()
-            );
-            if std::env::var("TEST_MODE").is_ok() {
-                TEST_OUTPUT.lock().unwrap().push(output);
-            } else {
-                println!("{}", output);
+            ),
+            FileProcessResult::Failed(path, error) => {
+                failed += 1;
+                format!("Failed: {} ({})", path.display(), error)
             }
+            _ => continue,
+        };
+        if std::env::var("TEST_MODE").is_ok() {
+            TEST_OUTPUT.lock().unwrap().push(output);
+        } else {
+            println!("{}", output);
         }
     }
 
+    if failed > 0 {
+        return Err(AppError::Materialize(failed));
+    }
     Ok(())
 }
 
@@ -53,9 +71,13 @@ mod tests {
     use crate::application::services::fish_service::FishService;
     use crate::application::services::link_service::LinkService;
     use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
     use crate::application::services::vscode_service::VSCodeService;
     use crate::error::AppError;
-    use crate::models::link::FileProcessResult;
+    use crate::models::diff::FileDiff;
+    use crate::models::link::{FileProcessResult, LinkOptions, MaterializeError};
+    use crate::models::plan::{LinkPlan, LoadPlan};
+    use crate::models::status::LinkStatus;
     use crate::presentation::cli::commands::materialize::{execute, MaterializeArgs};
     use async_trait::async_trait;
     use std::path::{Path, PathBuf};
@@ -69,7 +91,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import(&self) -> Result<(), AppError> {
+        async fn import<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -88,25 +114,169 @@ mod tests {
             &self,
             _source: &Path,
             _target: &Path,
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
 
+        async fn plan_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
         async fn materialize_dotfiles(
             &self,
             _target: &Path,
+            _source: Option<PathBuf>,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(self.materialize_result.clone())
         }
+
+        async fn restore_backup(
+            &self,
+            _run_id: Option<String>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn list_backups(&self) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn status_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn diff_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+            _paths: &[PathBuf],
+        ) -> Result<Vec<FileDiff>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn add_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn watch_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn config_vars(
+            &self,
+            _config_path: &Path,
+        ) -> Result<std::collections::BTreeMap<String, String>, AppError> {
+            Ok(std::collections::BTreeMap::new())
+        }
+
+        async fn configured_link<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+            _source: &'a Path,
+            target: &'a Path,
+        ) -> Result<(PathBuf, LinkOptions), AppError> {
+            Ok((target.to_path_buf(), LinkOptions::default()))
+        }
+
+        async fn encrypt_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
     }
 
     struct CustomMockLoadService;
 
     #[async_trait]
     impl LoadService for CustomMockLoadService {
-        async fn load(&self, _config_path: &Path, _target: &Path) -> Result<(), AppError> {
+        async fn load<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
+
+        async fn plan<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<LoadPlan, AppError> {
+            Ok(LoadPlan::default())
+        }
+
+        async fn check_permissions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
     }
 
     struct CustomMockDeployService;
@@ -143,7 +313,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import_extensions(&self) -> Result<(), AppError> {
+        async fn import_extensions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -152,6 +326,31 @@ mod tests {
         }
     }
 
+    struct CustomMockSyncService;
+
+    #[async_trait]
+    impl SyncService for CustomMockSyncService {
+        async fn repo_of(&self, _config_path: &Path) -> Result<PathBuf, AppError> {
+            Ok(PathBuf::new())
+        }
+
+        async fn pull(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn changes(&self, _repo: &Path) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn commit(&self, _repo: &Path, _changes: &[String]) -> Result<String, AppError> {
+            Ok(String::new())
+        }
+
+        async fn push(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
     struct CustomMockServiceProvider {
         brew_service: Arc<dyn BrewService>,
         link_service: Arc<dyn LinkService>,
@@ -159,6 +358,7 @@ mod tests {
         deploy_service: Arc<dyn DeployService>,
         fish_service: Arc<dyn FishService>,
         vscode_service: Arc<dyn VSCodeService>,
+        sync_service: Arc<dyn SyncService>,
     }
 
     impl CustomMockServiceProvider {
@@ -170,6 +370,7 @@ mod tests {
                 deploy_service: Arc::new(CustomMockDeployService) as Arc<dyn DeployService>,
                 fish_service: Arc::new(CustomMockFishService) as Arc<dyn FishService>,
                 vscode_service: Arc::new(CustomMockVSCodeService) as Arc<dyn VSCodeService>,
+                sync_service: Arc::new(CustomMockSyncService) as Arc<dyn SyncService>,
             }
         }
     }
@@ -198,6 +399,10 @@ mod tests {
         fn vscode_service(&self) -> Arc<dyn VSCodeService> {
             Arc::clone(&self.vscode_service)
         }
+
+        fn sync_service(&self) -> Arc<dyn SyncService> {
+            Arc::clone(&self.sync_service)
+        }
     }
 
     #[tokio::test]
@@ -211,6 +416,7 @@ mod tests {
 
         let args = MaterializeArgs {
             target: PathBuf::new(),
+            source: None,
         };
         let result = execute(args, mock_services.as_ref()).await;
         assert!(result.is_ok());
@@ -230,6 +436,7 @@ mod tests {
 
         let args = MaterializeArgs {
             target: PathBuf::new(),
+            source: None,
         };
 
         // Set environment variable to enable test mode
@@ -252,4 +459,24 @@ mod tests {
             output.contains("Materialized: /home/user/.bashrc (was linked to /dotfiles/.bashrc)")
         );
     }
+
+    #[tokio::test]
+    async fn test_execute_materialize_dotfiles_with_failure() {
+        let mock_link_service = Arc::new(CustomMockLinkService {
+            materialize_result: vec![FileProcessResult::Failed(
+                PathBuf::from("/home/user/.vimrc"),
+                MaterializeError::Dangling(PathBuf::from("/dotfiles/.vimrc")),
+            )],
+        });
+        let mock_services = Arc::new(CustomMockServiceProvider::new(
+            Arc::clone(&mock_link_service) as Arc<dyn LinkService>,
+        )) as Arc<dyn ServiceProvider>;
+
+        let args = MaterializeArgs {
+            target: PathBuf::new(),
+            source: Some(PathBuf::from("/dotfiles")),
+        };
+        let result = execute(args, mock_services.as_ref()).await;
+        assert!(matches!(result, Err(AppError::Materialize(1))));
+    }
 }
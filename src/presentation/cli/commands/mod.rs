@@ -1,7 +1,16 @@
+pub mod add;
 pub mod brew;
 pub mod deploy;
+pub mod diff;
+pub mod encrypt;
 pub mod fish;
 pub mod link;
 pub mod load;
 pub mod materialize;
+pub mod prune;
+pub mod restore;
+pub mod status;
+pub mod sync;
+pub mod unlink;
 pub mod vscode;
+pub mod watch;
@@ -0,0 +1,231 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::constants::{DEFAULT_CONFIG_TOML, TEST_HOME_DIR};
+use crate::error::AppError;
+use crate::models::link::{FileProcessResult, LinkOptions};
+use clap::{Args, ValueHint};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct PruneArgs {
+    #[arg(
+        value_hint = ValueHint::FilePath,
+        help = "The path to the dotfiles directory whose stale links should be removed",
+        value_name = "SOURCE_DOTFILES_DIR_PATH"
+    )]
+    source_path: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Prune the test directory instead of the home directory for testing purposes"
+    )]
+    test: bool,
+
+    #[arg(
+        long,
+        help = "Also skip files excluded by .gitignore files in the dotfiles directory"
+    )]
+    gitignore: bool,
+
+    #[arg(
+        long,
+        help = "Link whole directories that do not exist in the target yet"
+    )]
+    fold: bool,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::FilePath,
+        default_value = DEFAULT_CONFIG_TOML,
+        help = "The TOML file whose [vars] templates are rendered with",
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config: PathBuf,
+}
+
+pub async fn execute(
+    args: PruneArgs,
+    dry_run: bool,
+    services: &dyn ServiceProvider,
+) -> Result<(), AppError> {
+    let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+    let target = if args.test {
+        home.join(TEST_HOME_DIR)
+    } else {
+        home
+    };
+    let options = LinkOptions {
+        gitignore: args.gitignore,
+        fold: args.fold,
+        vars: services.link_service().config_vars(&args.config).await?,
+        ..Default::default()
+    };
+
+    if dry_run {
+        let plan = services
+            .link_service()
+            .plan_prune_dotfiles(&args.source_path, &target, &options)
+            .await?;
+        println!(
+            "Dry run: pruning {} from {}",
+            plan.source.display(),
+            plan.target.display()
+        );
+        for action in &plan.actions {
+            println!("  {}", action);
+        }
+        return Ok(());
+    }
+
+    let results = services
+        .link_service()
+        .prune_dotfiles(&args.source_path, &target, &options)
+        .await?;
+
+    if results.is_empty() {
+        println!("Nothing to prune");
+    }
+    for result in results {
+        match result {
+            FileProcessResult::Unlinked(link, src) => {
+                println!(
+                    "Unlinked: {} (was linked to {})",
+                    link.display(),
+                    src.display()
+                );
+            }
+            FileProcessResult::Removed(path) => {
+                println!("Removed: {}", path.display());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::services::brew_service::BrewService;
+    use crate::application::services::deploy_service::DeployService;
+    use crate::application::services::fish_service::FishService;
+    use crate::application::services::link_service::LinkService;
+    use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
+    use crate::application::services::vscode_service::VSCodeService;
+    use crate::models::diff::FileDiff;
+    use crate::models::plan::{LinkAction, LinkPlan};
+    use crate::models::status::LinkStatus;
+    use async_trait::async_trait;
+    use mockall::predicate::*;
+    use mockall::*;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    mock! {
+        pub ServiceProvider {}
+        impl ServiceProvider for ServiceProvider {
+            fn brew_service(&self) -> Arc<dyn BrewService>;
+            fn link_service(&self) -> Arc<dyn LinkService>;
+            fn load_service(&self) -> Arc<dyn LoadService>;
+            fn deploy_service(&self) -> Arc<dyn DeployService>;
+            fn fish_service(&self) -> Arc<dyn FishService>;
+            fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
+        }
+    }
+
+    mock! {
+        pub LinkService {}
+        #[async_trait]
+        impl LinkService for LinkService {
+            async fn link_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+            async fn status_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions, paths: &[PathBuf]) -> Result<Vec<FileDiff>, AppError>;
+            async fn materialize_dotfiles(&self, target: &Path, source: Option<PathBuf>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn restore_backup(&self, run_id: Option<String>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn list_backups(&self) -> Result<Vec<String>, AppError>;
+            async fn add_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn watch_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<(), AppError>;
+            async fn config_vars(&self, config_path: &Path) -> Result<std::collections::BTreeMap<String, String>, AppError>;
+            async fn configured_link<'a>(&self, config_path: &'a Path, profile: Option<&'a str>, source: &'a Path, target: &'a Path) -> Result<(PathBuf, LinkOptions), AppError>;
+        }
+    }
+
+    fn service_provider(mock_link_service: MockLinkService) -> MockServiceProvider {
+        let mut mock_service_provider = MockServiceProvider::new();
+        mock_service_provider
+            .expect_link_service()
+            .return_const(Arc::new(mock_link_service) as Arc<dyn LinkService>);
+        mock_service_provider
+    }
+
+    #[tokio::test]
+    async fn test_execute_prune() {
+        let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_config_vars()
+            .returning(|_| Ok(Default::default()));
+        mock_link_service
+            .expect_prune_dotfiles()
+            .with(eq(PathBuf::from("/src")), always(), always())
+            .times(1)
+            .returning(|source, target, _| {
+                Ok(vec![FileProcessResult::Unlinked(
+                    target.join(".old"),
+                    source.join(".old"),
+                )])
+            });
+        mock_link_service.expect_plan_prune_dotfiles().times(0);
+
+        let args = PruneArgs {
+            source_path: PathBuf::from("/src"),
+            test: true,
+            gitignore: false,
+            fold: false,
+            config: PathBuf::from("/config.toml"),
+        };
+
+        let result = execute(args, false, &service_provider(mock_link_service)).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_execute_prune_dry_run() {
+        let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_config_vars()
+            .returning(|_| Ok(Default::default()));
+        mock_link_service
+            .expect_plan_prune_dotfiles()
+            .times(1)
+            .returning(|source, target, options| {
+                Ok(LinkPlan {
+                    source: source.to_path_buf(),
+                    target: target.to_path_buf(),
+                    actions: vec![LinkAction::Remove(target.join(".gitconfig"))],
+                    options: options.clone(),
+                })
+            });
+        mock_link_service.expect_prune_dotfiles().times(0);
+
+        let args = PruneArgs {
+            source_path: PathBuf::from("/src"),
+            test: true,
+            gitignore: false,
+            fold: false,
+            config: PathBuf::from("/config.toml"),
+        };
+
+        let result = execute(args, true, &service_provider(mock_link_service)).await;
+        assert!(result.is_ok());
+    }
+}
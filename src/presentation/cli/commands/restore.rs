@@ -0,0 +1,45 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::error::AppError;
+use crate::models::link::FileProcessResult;
+use clap::Args;
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    #[arg(
+        help = "The backup run to restore, defaults to the most recent one",
+        value_name = "RUN_ID"
+    )]
+    run_id: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "List the recorded backup runs instead of restoring"
+    )]
+    list: bool,
+}
+
+pub async fn execute(args: RestoreArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+    if args.list {
+        for run_id in services.link_service().list_backups().await? {
+            println!("{}", run_id);
+        }
+        return Ok(());
+    }
+
+    let results = services.link_service().restore_backup(args.run_id).await?;
+
+    for result in results {
+        match result {
+            FileProcessResult::Restored(path, backup) => {
+                println!("Restored: {} (from {})", path.display(), backup.display());
+            }
+            FileProcessResult::Skipped(path) => {
+                println!("Skipped: {} (a file already exists)", path.display());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::constants::{DEFAULT_CONFIG_TOML, TEST_HOME_DIR};
+use crate::error::AppError;
+use crate::models::link::LinkOptions;
+use clap::{Args, ValueHint};
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct StatusArgs {
+    #[arg(
+        value_hint = ValueHint::FilePath,
+        help = "The path to the dotfiles directory",
+        value_name = "SOURCE_DOTFILES_DIR_PATH"
+    )]
+    source_path: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Check the test directory instead of the home directory for testing purposes"
+    )]
+    test: bool,
+
+    #[arg(short, long, help = "Also list dotfiles that are linked as expected")]
+    all: bool,
+
+    #[arg(
+        long,
+        help = "Also skip files excluded by .gitignore files in the dotfiles directory"
+    )]
+    gitignore: bool,
+
+    #[arg(
+        long,
+        help = "Link whole directories that do not exist in the target yet"
+    )]
+    fold: bool,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::FilePath,
+        help = "Also check the permissions declared in this TOML file, and render templates with its [vars]",
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        env = "WIDOTS_PROFILE",
+        help = "The profile in the TOML file whose permissions to check, by default the one listing this host",
+        value_name = "PROFILE"
+    )]
+    profile: Option<String>,
+}
+
+pub async fn execute(args: StatusArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+    let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+    let target = if args.test {
+        home.join(TEST_HOME_DIR)
+    } else {
+        home
+    };
+    let options = LinkOptions {
+        gitignore: args.gitignore,
+        fold: args.fold,
+        vars: services
+            .link_service()
+            .config_vars(
+                args.config
+                    .as_deref()
+                    .unwrap_or(Path::new(DEFAULT_CONFIG_TOML)),
+            )
+            .await?,
+        ..Default::default()
+    };
+
+    let mut statuses = services
+        .link_service()
+        .status_dotfiles(&args.source_path, &target, &options)
+        .await?;
+    if let Some(config) = &args.config {
+        statuses.extend(
+            services
+                .load_service()
+                .check_permissions(config, &target, args.profile.as_deref())
+                .await?,
+        );
+    }
+
+    let drift = statuses.iter().filter(|status| status.is_drift()).count();
+    for status in &statuses {
+        if args.all || status.is_drift() {
+            println!("{}", status);
+        }
+    }
+
+    if drift > 0 {
+        return Err(AppError::Drift(drift));
+    }
+
+    println!("All {} dotfiles are linked", statuses.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::services::brew_service::BrewService;
+    use crate::application::services::deploy_service::DeployService;
+    use crate::application::services::fish_service::FishService;
+    use crate::application::services::link_service::LinkService;
+    use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
+    use crate::application::services::vscode_service::VSCodeService;
+    use crate::models::diff::FileDiff;
+    use crate::models::link::FileProcessResult;
+    use crate::models::plan::{LinkPlan, LoadPlan};
+    use crate::models::status::LinkStatus;
+    use async_trait::async_trait;
+    use mockall::predicate::*;
+    use mockall::*;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    mock! {
+        pub ServiceProvider {}
+        impl ServiceProvider for ServiceProvider {
+            fn brew_service(&self) -> Arc<dyn BrewService>;
+            fn link_service(&self) -> Arc<dyn LinkService>;
+            fn load_service(&self) -> Arc<dyn LoadService>;
+            fn deploy_service(&self) -> Arc<dyn DeployService>;
+            fn fish_service(&self) -> Arc<dyn FishService>;
+            fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
+        }
+    }
+
+    mock! {
+        pub LinkService {}
+        #[async_trait]
+        impl LinkService for LinkService {
+            async fn link_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+            async fn status_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions, paths: &[PathBuf]) -> Result<Vec<FileDiff>, AppError>;
+            async fn materialize_dotfiles(&self, target: &Path, source: Option<PathBuf>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn restore_backup(&self, run_id: Option<String>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn list_backups(&self) -> Result<Vec<String>, AppError>;
+            async fn add_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn watch_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<(), AppError>;
+            async fn config_vars(&self, config_path: &Path) -> Result<std::collections::BTreeMap<String, String>, AppError>;
+            async fn configured_link<'a>(&self, config_path: &'a Path, profile: Option<&'a str>, source: &'a Path, target: &'a Path) -> Result<(PathBuf, LinkOptions), AppError>;
+        }
+    }
+
+    mock! {
+        pub LoadService {}
+        #[async_trait]
+        impl LoadService for LoadService {
+            async fn load<'a>(&self, config_path: &'a Path, target: &'a Path, profile: Option<&'a str>) -> Result<(), AppError>;
+            async fn plan<'a>(&self, config_path: &'a Path, target: &'a Path, profile: Option<&'a str>) -> Result<LoadPlan, AppError>;
+            async fn check_permissions<'a>(&self, config_path: &'a Path, target: &'a Path, profile: Option<&'a str>) -> Result<Vec<LinkStatus>, AppError>;
+        }
+    }
+
+    fn service_provider(statuses: Vec<LinkStatus>) -> MockServiceProvider {
+        let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_config_vars()
+            .returning(|_| Ok(Default::default()));
+        mock_link_service
+            .expect_status_dotfiles()
+            .with(eq(PathBuf::from("/src")), always(), always())
+            .returning(move |_, _, _| Ok(statuses.clone()));
+
+        let mut mock_service_provider = MockServiceProvider::new();
+        mock_service_provider
+            .expect_link_service()
+            .return_const(Arc::new(mock_link_service) as Arc<dyn LinkService>);
+        mock_service_provider
+    }
+
+    #[tokio::test]
+    async fn test_execute_status_clean() {
+        let services = service_provider(vec![LinkStatus::Linked(
+            PathBuf::from("/src/.bashrc"),
+            PathBuf::from("/dst/.bashrc"),
+        )]);
+
+        let args = StatusArgs {
+            source_path: PathBuf::from("/src"),
+            test: true,
+            all: true,
+            gitignore: false,
+            fold: false,
+            config: None,
+            profile: None,
+        };
+
+        let result = execute(args, &services).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_execute_status_with_drift() {
+        let services = service_provider(vec![
+            LinkStatus::Linked(PathBuf::from("/src/.bashrc"), PathBuf::from("/dst/.bashrc")),
+            LinkStatus::Missing(PathBuf::from("/src/.vimrc"), PathBuf::from("/dst/.vimrc")),
+            LinkStatus::Stray(PathBuf::from("/dst/.zshrc"), PathBuf::from("/src/.zshrc")),
+        ]);
+
+        let args = StatusArgs {
+            source_path: PathBuf::from("/src"),
+            test: true,
+            all: false,
+            gitignore: false,
+            fold: false,
+            config: None,
+            profile: None,
+        };
+
+        let result = execute(args, &services).await;
+        assert!(matches!(result, Err(AppError::Drift(2))));
+    }
+
+    #[tokio::test]
+    async fn test_execute_status_with_permissions() {
+        let mut services = service_provider(vec![LinkStatus::Linked(
+            PathBuf::from("/src/.ssh/config"),
+            PathBuf::from("/dst/.ssh/config"),
+        )]);
+        let mut mock_load_service = MockLoadService::new();
+        mock_load_service
+            .expect_check_permissions()
+            .withf(|config, _, profile| {
+                config == Path::new("/src/widots.toml") && *profile == Some("work")
+            })
+            .times(1)
+            .returning(|_, target, _| {
+                Ok(vec![LinkStatus::WrongMode(
+                    target.join(".ssh/config"),
+                    0o600,
+                    0o644,
+                )])
+            });
+        services
+            .expect_load_service()
+            .return_const(Arc::new(mock_load_service) as Arc<dyn LoadService>);
+
+        let args = StatusArgs {
+            source_path: PathBuf::from("/src"),
+            test: true,
+            all: false,
+            gitignore: false,
+            fold: false,
+            config: Some(PathBuf::from("/src/widots.toml")),
+            profile: Some("work".to_string()),
+        };
+
+        let result = execute(args, &services).await;
+        assert!(matches!(result, Err(AppError::Drift(1))));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::constants::DEFAULT_CONFIG_TOML;
+use crate::constants::TEST_HOME_DIR;
+use crate::error::AppError;
+use clap::{Args, ValueHint};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct SyncArgs {
+    #[arg(
+        value_hint = ValueHint::FilePath,
+        help = "The path to the TOML file to load after pulling",
+        default_value = DEFAULT_CONFIG_TOML,
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config_toml: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::DirPath,
+        help = "The dotfiles git repository, by default the one holding the TOML file",
+        value_name = "REPO_DIR_PATH"
+    )]
+    repo: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        help = "Link to the test directory instead of the home directory for testing purposes"
+    )]
+    test: bool,
+
+    #[arg(
+        short,
+        long,
+        env = "WIDOTS_PROFILE",
+        help = "The profile in the TOML file to load, by default the one listing this host",
+        value_name = "PROFILE"
+    )]
+    profile: Option<String>,
+
+    #[arg(long, help = "Commit local changes with a generated message")]
+    commit: bool,
+
+    #[arg(long, help = "Do not push to the remote after syncing")]
+    no_push: bool,
+}
+
+pub async fn execute(args: SyncArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+    let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+    let target = if args.test {
+        home.join(TEST_HOME_DIR)
+    } else {
+        home
+    };
+    let sync_service = services.sync_service();
+    let repo = match args.repo {
+        Some(repo) => repo,
+        None => sync_service.repo_of(&args.config_toml).await?,
+    };
+
+    println!("Pulling {}", repo.display());
+    sync_service.pull(&repo).await?;
+
+    services
+        .load_service()
+        .load(&args.config_toml, &target, args.profile.as_deref())
+        .await?;
+
+    let changes = sync_service.changes(&repo).await?;
+    if changes.is_empty() {
+        println!("No local changes");
+    } else {
+        println!("Local changes in {}:", repo.display());
+        for change in &changes {
+            println!("  {}", change);
+        }
+    }
+
+    if args.commit && !changes.is_empty() {
+        let message = sync_service.commit(&repo, &changes).await?;
+        println!("Committed: {}", message.lines().next().unwrap_or_default());
+    }
+
+    if !args.no_push {
+        sync_service.push(&repo).await?;
+        println!("Pushed {}", repo.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::services::brew_service::BrewService;
+    use crate::application::services::deploy_service::DeployService;
+    use crate::application::services::fish_service::FishService;
+    use crate::application::services::link_service::LinkService;
+    use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
+    use crate::application::services::vscode_service::VSCodeService;
+    use crate::models::plan::LoadPlan;
+    use crate::models::status::LinkStatus;
+    use async_trait::async_trait;
+    use mockall::predicate::*;
+    use mockall::*;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    mock! {
+        pub ServiceProvider {}
+        impl ServiceProvider for ServiceProvider {
+            fn brew_service(&self) -> Arc<dyn BrewService>;
+            fn link_service(&self) -> Arc<dyn LinkService>;
+            fn load_service(&self) -> Arc<dyn LoadService>;
+            fn deploy_service(&self) -> Arc<dyn DeployService>;
+            fn fish_service(&self) -> Arc<dyn FishService>;
+            fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
+        }
+    }
+
+    mock! {
+        pub LoadService {}
+        #[async_trait]
+        impl LoadService for LoadService {
+            async fn load<'a>(&self, config_path: &'a Path, target: &'a Path, profile: Option<&'a str>) -> Result<(), AppError>;
+            async fn plan<'a>(&self, config_path: &'a Path, target: &'a Path, profile: Option<&'a str>) -> Result<LoadPlan, AppError>;
+            async fn check_permissions<'a>(&self, config_path: &'a Path, target: &'a Path, profile: Option<&'a str>) -> Result<Vec<LinkStatus>, AppError>;
+        }
+    }
+
+    mock! {
+        pub SyncService {}
+        #[async_trait]
+        impl SyncService for SyncService {
+            async fn repo_of(&self, config_path: &Path) -> Result<PathBuf, AppError>;
+            async fn pull(&self, repo: &Path) -> Result<(), AppError>;
+            async fn changes(&self, repo: &Path) -> Result<Vec<String>, AppError>;
+            async fn commit(&self, repo: &Path, changes: &[String]) -> Result<String, AppError>;
+            async fn push(&self, repo: &Path) -> Result<(), AppError>;
+        }
+    }
+
+    fn service_provider(
+        mock_load_service: MockLoadService,
+        mock_sync_service: MockSyncService,
+    ) -> MockServiceProvider {
+        let mut mock_service_provider = MockServiceProvider::new();
+        mock_service_provider
+            .expect_load_service()
+            .return_const(Arc::new(mock_load_service) as Arc<dyn LoadService>);
+        mock_service_provider
+            .expect_sync_service()
+            .return_const(Arc::new(mock_sync_service) as Arc<dyn SyncService>);
+        mock_service_provider
+    }
+
+    #[tokio::test]
+    async fn test_execute_sync() {
+        let repo = PathBuf::from("/dotfiles");
+        let mut seq = Sequence::new();
+        let mut mock_sync_service = MockSyncService::new();
+        let mut mock_load_service = MockLoadService::new();
+        mock_sync_service
+            .expect_repo_of()
+            .with(eq(PathBuf::from("/dotfiles/config.toml")))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(PathBuf::from("/dotfiles")));
+        mock_sync_service
+            .expect_pull()
+            .with(eq(repo.clone()))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+        mock_load_service
+            .expect_load()
+            .withf(|config_path, _, profile| {
+                config_path == Path::new("/dotfiles/config.toml") && *profile == Some("work")
+            })
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _, _| Ok(()));
+        mock_sync_service
+            .expect_changes()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(vec![" M .vimrc".to_string()]));
+        mock_sync_service
+            .expect_commit()
+            .with(eq(repo.clone()), eq(vec![" M .vimrc".to_string()]))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok("Update .vimrc\n\n.vimrc".to_string()));
+        mock_sync_service
+            .expect_push()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+
+        let args = SyncArgs {
+            config_toml: PathBuf::from("/dotfiles/config.toml"),
+            repo: None,
+            test: true,
+            profile: Some("work".to_string()),
+            commit: true,
+            no_push: false,
+        };
+
+        let result = execute(
+            args,
+            &service_provider(mock_load_service, mock_sync_service),
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_execute_sync_needs_a_repo() {
+        let mut mock_sync_service = MockSyncService::new();
+        let mut mock_load_service = MockLoadService::new();
+        mock_sync_service
+            .expect_repo_of()
+            .times(1)
+            .returning(|config_path| Err(AppError::SyncRepoNotFound(config_path.to_path_buf())));
+        mock_sync_service.expect_pull().times(0);
+        mock_load_service.expect_load().times(0);
+
+        let args = SyncArgs {
+            config_toml: PathBuf::from("/etc/widots/config.toml"),
+            repo: None,
+            test: true,
+            profile: None,
+            commit: false,
+            no_push: false,
+        };
+
+        let result = execute(
+            args,
+            &service_provider(mock_load_service, mock_sync_service),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::SyncRepoNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_execute_sync_stops_on_conflicts() {
+        let mut mock_sync_service = MockSyncService::new();
+        let mut mock_load_service = MockLoadService::new();
+        mock_sync_service.expect_pull().times(1).returning(|repo| {
+            Err(AppError::SyncConflict(
+                repo.to_path_buf(),
+                vec![".vimrc".to_string()],
+            ))
+        });
+        mock_load_service.expect_load().times(0);
+        mock_sync_service.expect_commit().times(0);
+        mock_sync_service.expect_push().times(0);
+
+        let args = SyncArgs {
+            config_toml: PathBuf::from("/dotfiles/config.toml"),
+            repo: Some(PathBuf::from("/repo")),
+            test: true,
+            profile: None,
+            commit: true,
+            no_push: false,
+        };
+
+        let result = execute(
+            args,
+            &service_provider(mock_load_service, mock_sync_service),
+        )
+        .await;
+        assert!(
+            matches!(result, Err(AppError::SyncConflict(repo, _)) if repo == Path::new("/repo"))
+        );
+    }
+}
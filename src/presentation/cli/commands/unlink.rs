@@ -0,0 +1,75 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::constants::TEST_HOME_DIR;
+use crate::error::AppError;
+use crate::models::link::FileProcessResult;
+use clap::{Args, ValueHint};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct UnlinkArgs {
+    #[arg(
+        value_hint = ValueHint::FilePath,
+        help = "The path to the dotfiles directory whose links should be removed",
+        value_name = "SOURCE_DOTFILES_DIR_PATH"
+    )]
+    source_path: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Unlink from the test directory instead of the home directory for testing purposes"
+    )]
+    test: bool,
+}
+
+pub async fn execute(
+    args: UnlinkArgs,
+    dry_run: bool,
+    services: &dyn ServiceProvider,
+) -> Result<(), AppError> {
+    let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+    let target = if args.test {
+        home.join(TEST_HOME_DIR)
+    } else {
+        home
+    };
+
+    if dry_run {
+        let plan = services
+            .link_service()
+            .plan_unlink_dotfiles(&args.source_path, &target)
+            .await?;
+        println!(
+            "Dry run: unlinking {} from {}",
+            plan.source.display(),
+            plan.target.display()
+        );
+        for action in &plan.actions {
+            println!("  {}", action);
+        }
+        return Ok(());
+    }
+
+    let results = services
+        .link_service()
+        .unlink_dotfiles(&args.source_path, &target)
+        .await?;
+
+    for result in results {
+        match result {
+            FileProcessResult::Unlinked(link, src) => {
+                println!(
+                    "Unlinked: {} (was linked to {})",
+                    link.display(),
+                    src.display()
+                );
+            }
+            FileProcessResult::Removed(path) => {
+                println!("Removed directory: {}", path.display());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
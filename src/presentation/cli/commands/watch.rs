@@ -0,0 +1,192 @@
+use crate::application::service_provider::ServiceProvider;
+use crate::constants::{DEFAULT_CONFIG_TOML, TEST_HOME_DIR};
+use crate::error::AppError;
+use crate::models::link::{ConflictStrategy, LinkMethod, LinkOptions};
+use clap::{Args, ValueHint};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct WatchArgs {
+    #[arg(
+        value_hint = ValueHint::FilePath,
+        help = "The path to the dotfiles directory to watch",
+        value_name = "SOURCE_DOTFILES_DIR_PATH"
+    )]
+    source_path: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "Link to the test directory instead of the home directory for testing purposes"
+    )]
+    test: bool,
+
+    #[arg(
+        long,
+        help = "Also skip files excluded by .gitignore files in the dotfiles directory"
+    )]
+    gitignore: bool,
+
+    #[arg(
+        long,
+        help = "Link whole directories that do not exist in the target yet"
+    )]
+    fold: bool,
+
+    #[arg(long, help = "Create links with paths relative to their directory")]
+    relative: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ConflictStrategy::Backup,
+        help = "What to do when a target file already exists, other than ask"
+    )]
+    on_conflict: ConflictStrategy,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LinkMethod::Symlink,
+        help = "How to place each file in the target"
+    )]
+    method: LinkMethod,
+
+    #[arg(
+        long,
+        default_value = "",
+        help = "Skip files whose names contain any of these characters",
+        value_name = "CHARS"
+    )]
+    deny_chars: String,
+
+    #[arg(
+        short,
+        long,
+        value_hint = ValueHint::FilePath,
+        default_value = DEFAULT_CONFIG_TOML,
+        help = "The TOML file whose [vars] templates are rendered with",
+        value_name = "CONFIG_TOML_FILE_PATH"
+    )]
+    config: PathBuf,
+}
+
+pub async fn execute(args: WatchArgs, services: &dyn ServiceProvider) -> Result<(), AppError> {
+    let home = dirs::home_dir().ok_or(AppError::DirectoryNotFound)?;
+    let target = if args.test {
+        home.join(TEST_HOME_DIR)
+    } else {
+        home
+    };
+    let options = LinkOptions {
+        gitignore: args.gitignore,
+        fold: args.fold,
+        relative: args.relative,
+        on_conflict: args.on_conflict,
+        method: args.method,
+        deny_chars: args.deny_chars.chars().collect(),
+        vars: services.link_service().config_vars(&args.config).await?,
+        ..Default::default()
+    };
+
+    services
+        .link_service()
+        .watch_dotfiles(&args.source_path, &target, &options)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::services::brew_service::BrewService;
+    use crate::application::services::deploy_service::DeployService;
+    use crate::application::services::fish_service::FishService;
+    use crate::application::services::link_service::LinkService;
+    use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
+    use crate::application::services::vscode_service::VSCodeService;
+    use crate::models::diff::FileDiff;
+    use crate::models::link::FileProcessResult;
+    use crate::models::plan::LinkPlan;
+    use crate::models::status::LinkStatus;
+    use async_trait::async_trait;
+    use mockall::predicate::*;
+    use mockall::*;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    mock! {
+        pub ServiceProvider {}
+        impl ServiceProvider for ServiceProvider {
+            fn brew_service(&self) -> Arc<dyn BrewService>;
+            fn link_service(&self) -> Arc<dyn LinkService>;
+            fn load_service(&self) -> Arc<dyn LoadService>;
+            fn deploy_service(&self) -> Arc<dyn DeployService>;
+            fn fish_service(&self) -> Arc<dyn FishService>;
+            fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
+        }
+    }
+
+    mock! {
+        pub LinkService {}
+        #[async_trait]
+        impl LinkService for LinkService {
+            async fn link_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_unlink_dotfiles(&self, source: &Path, target: &Path) -> Result<LinkPlan, AppError>;
+            async fn status_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<LinkStatus>, AppError>;
+            async fn diff_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions, paths: &[PathBuf]) -> Result<Vec<FileDiff>, AppError>;
+            async fn materialize_dotfiles(&self, target: &Path, source: Option<PathBuf>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn restore_backup(&self, run_id: Option<String>) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn list_backups(&self) -> Result<Vec<String>, AppError>;
+            async fn add_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn encrypt_dotfiles(&self, source: &Path, target: &Path, paths: &[PathBuf], options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<Vec<FileProcessResult>, AppError>;
+            async fn plan_prune_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<LinkPlan, AppError>;
+            async fn watch_dotfiles(&self, source: &Path, target: &Path, options: &LinkOptions) -> Result<(), AppError>;
+            async fn config_vars(&self, config_path: &Path) -> Result<std::collections::BTreeMap<String, String>, AppError>;
+            async fn configured_link<'a>(&self, config_path: &'a Path, profile: Option<&'a str>, source: &'a Path, target: &'a Path) -> Result<(PathBuf, LinkOptions), AppError>;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_watch() {
+        let mut mock_link_service = MockLinkService::new();
+        mock_link_service
+            .expect_config_vars()
+            .returning(|_| Ok(Default::default()));
+        mock_link_service
+            .expect_watch_dotfiles()
+            .with(
+                eq(PathBuf::from("/src")),
+                eq(dirs::home_dir().unwrap().join(TEST_HOME_DIR)),
+                function(|options: &LinkOptions| {
+                    options.fold && options.on_conflict == ConflictStrategy::Skip
+                }),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let mut mock_service_provider = MockServiceProvider::new();
+        mock_service_provider
+            .expect_link_service()
+            .return_const(Arc::new(mock_link_service) as Arc<dyn LinkService>);
+
+        let args = WatchArgs {
+            source_path: PathBuf::from("/src"),
+            test: true,
+            gitignore: false,
+            fold: true,
+            relative: false,
+            on_conflict: ConflictStrategy::Skip,
+            method: LinkMethod::Symlink,
+            deny_chars: String::new(),
+            config: PathBuf::from("/config.toml"),
+        };
+
+        let result = execute(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+}
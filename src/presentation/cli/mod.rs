@@ -15,16 +15,41 @@ pub struct Args {
 
     #[clap(short, long, global = true, action = ArgAction::Count, help = "Sets the level of verbosity")]
     pub verbose: u8,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Print the planned changes without touching the filesystem"
+    )]
+    pub dry_run: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     #[command(about = "Link dotfiles to home directory")]
     Link(commands::link::LinkArgs),
+    #[command(about = "Remove links to dotfiles from home directory")]
+    Unlink(commands::unlink::UnlinkArgs),
+    #[command(about = "Remove links and files the dotfiles directory no longer produces")]
+    Prune(commands::prune::PruneArgs),
+    #[command(about = "Keep dotfiles linked while the dotfiles directory changes")]
+    Watch(commands::watch::WatchArgs),
+    #[command(about = "Move files into the dotfiles directory and link them back")]
+    Add(commands::add::AddArgs),
+    #[command(about = "Encrypt secret files into the dotfiles directory")]
+    Encrypt(commands::encrypt::EncryptArgs),
+    #[command(about = "Report dotfiles whose links have drifted from the source")]
+    Status(commands::status::StatusArgs),
+    #[command(about = "Show how deployed copies and templates differ from their source")]
+    Diff(commands::diff::DiffArgs),
     #[command(about = "Materialize dotfiles to destination directory")]
     Materialize(commands::materialize::MaterializeArgs),
     #[command(about = "Execute procedures from TOML file")]
     Load(commands::load::LoadArgs),
+    #[command(about = "Pull the dotfiles repository, load it, then commit and push local changes")]
+    Sync(commands::sync::SyncArgs),
+    #[command(about = "Restore files backed up when linking")]
+    Restore(commands::restore::RestoreArgs),
     #[command(about = "Builds and deploys the executable to the local machine")]
     Deploy,
     #[command(about = "Manage Homebrew packages")]
@@ -36,12 +61,44 @@ enum Commands {
 }
 
 pub async fn run<S: ServiceProvider>(args: Args, service_provider: &S) -> Result<(), AppError> {
+    // The flag is global so it can go before the subcommand, but only these can honour it
+    let supports_dry_run = matches!(
+        args.command,
+        Commands::Link(_) | Commands::Unlink(_) | Commands::Prune(_) | Commands::Load(_)
+    );
+    if args.dry_run && !supports_dry_run {
+        return Err(AppError::DryRunUnsupported);
+    }
+
     match args.command {
-        Commands::Link(link_args) => commands::link::execute(link_args, service_provider).await,
+        Commands::Link(link_args) => {
+            commands::link::execute(link_args, args.dry_run, service_provider).await
+        }
+        Commands::Unlink(unlink_args) => {
+            commands::unlink::execute(unlink_args, args.dry_run, service_provider).await
+        }
+        Commands::Prune(prune_args) => {
+            commands::prune::execute(prune_args, args.dry_run, service_provider).await
+        }
+        Commands::Watch(watch_args) => commands::watch::execute(watch_args, service_provider).await,
+        Commands::Add(add_args) => commands::add::execute(add_args, service_provider).await,
+        Commands::Encrypt(encrypt_args) => {
+            commands::encrypt::execute(encrypt_args, service_provider).await
+        }
+        Commands::Status(status_args) => {
+            commands::status::execute(status_args, service_provider).await
+        }
+        Commands::Diff(diff_args) => commands::diff::execute(diff_args, service_provider).await,
         Commands::Materialize(materialize_args) => {
             commands::materialize::execute(materialize_args, service_provider).await
         }
-        Commands::Load(load_args) => commands::load::execute(load_args, service_provider).await,
+        Commands::Load(load_args) => {
+            commands::load::execute(load_args, args.dry_run, service_provider).await
+        }
+        Commands::Sync(sync_args) => commands::sync::execute(sync_args, service_provider).await,
+        Commands::Restore(restore_args) => {
+            commands::restore::execute(restore_args, service_provider).await
+        }
         Commands::Deploy => commands::deploy::execute(service_provider).await,
         Commands::Brew(brew_args) => commands::brew::execute(brew_args, service_provider).await,
         Commands::Fish(fish_args) => commands::fish::execute(fish_args, service_provider).await,
@@ -61,14 +118,18 @@ mod tests {
     use crate::application::services::fish_service::FishService;
     use crate::application::services::link_service::LinkService;
     use crate::application::services::load_service::LoadService;
+    use crate::application::services::sync_service::SyncService;
     use crate::application::services::vscode_service::VSCodeService;
     use crate::constants::APP_NAME;
-    use crate::models::link::FileProcessResult;
+    use crate::models::diff::FileDiff;
+    use crate::models::link::{FileProcessResult, LinkOptions};
+    use crate::models::plan::{LinkPlan, LoadPlan};
+    use crate::models::status::LinkStatus;
     use async_trait::async_trait;
     use clap::Parser;
     use mockall::predicate::*;
     use mockall::*;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
     struct CustomMockBrewService;
 
@@ -78,7 +139,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import(&self) -> Result<(), AppError> {
+        async fn import<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -95,13 +160,134 @@ mod tests {
             &self,
             _source: &Path,
             _target: &Path,
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
 
+        async fn plan_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
         async fn materialize_dotfiles(
             &self,
             _target: &Path,
+            _source: Option<PathBuf>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn restore_backup(
+            &self,
+            _run_id: Option<String>,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn list_backups(&self) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_unlink_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn status_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn diff_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+            _paths: &[PathBuf],
+        ) -> Result<Vec<FileDiff>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn add_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<Vec<FileProcessResult>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn plan_prune_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<LinkPlan, AppError> {
+            Ok(LinkPlan::default())
+        }
+
+        async fn watch_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _options: &LinkOptions,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn config_vars(
+            &self,
+            _config_path: &Path,
+        ) -> Result<std::collections::BTreeMap<String, String>, AppError> {
+            Ok(std::collections::BTreeMap::new())
+        }
+
+        async fn configured_link<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+            _source: &'a Path,
+            target: &'a Path,
+        ) -> Result<(PathBuf, LinkOptions), AppError> {
+            Ok((target.to_path_buf(), LinkOptions::default()))
+        }
+
+        async fn encrypt_dotfiles(
+            &self,
+            _source: &Path,
+            _target: &Path,
+            _paths: &[PathBuf],
+            _options: &LinkOptions,
         ) -> Result<Vec<FileProcessResult>, AppError> {
             Ok(vec![])
         }
@@ -111,9 +297,32 @@ mod tests {
 
     #[async_trait]
     impl LoadService for CustomMockLoadService {
-        async fn load(&self, _config_path: &Path, _target: &Path) -> Result<(), AppError> {
+        async fn load<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
+
+        async fn plan<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<LoadPlan, AppError> {
+            Ok(LoadPlan::default())
+        }
+
+        async fn check_permissions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _target: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<Vec<LinkStatus>, AppError> {
+            Ok(vec![])
+        }
     }
 
     struct CustomMockDeployService;
@@ -150,7 +359,11 @@ mod tests {
             Ok(())
         }
 
-        async fn import_extensions(&self) -> Result<(), AppError> {
+        async fn import_extensions<'a>(
+            &self,
+            _config_path: &'a Path,
+            _profile: Option<&'a str>,
+        ) -> Result<(), AppError> {
             Ok(())
         }
 
@@ -159,6 +372,31 @@ mod tests {
         }
     }
 
+    struct CustomMockSyncService;
+
+    #[async_trait]
+    impl SyncService for CustomMockSyncService {
+        async fn repo_of(&self, _config_path: &Path) -> Result<PathBuf, AppError> {
+            Ok(PathBuf::new())
+        }
+
+        async fn pull(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn changes(&self, _repo: &Path) -> Result<Vec<String>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn commit(&self, _repo: &Path, _changes: &[String]) -> Result<String, AppError> {
+            Ok(String::new())
+        }
+
+        async fn push(&self, _repo: &Path) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
     mock! {
         pub ServiceProvider {}
 
@@ -170,6 +408,7 @@ mod tests {
             fn brew_service(&self) -> Arc<dyn BrewService>;
             fn fish_service(&self) -> Arc<dyn FishService>;
             fn vscode_service(&self) -> Arc<dyn VSCodeService>;
+            fn sync_service(&self) -> Arc<dyn SyncService>;
         }
     }
 
@@ -187,6 +426,158 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_dry_run_flag_is_global() {
+        let args = Args::parse_from([APP_NAME, "link", "--dry-run", "/src"]);
+        assert!(args.dry_run);
+
+        let args = Args::parse_from([APP_NAME, "--dry-run", "load"]);
+        assert!(args.dry_run);
+
+        let args = Args::parse_from([APP_NAME, "link", "/src"]);
+        assert!(!args.dry_run);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_is_refused_where_unsupported() {
+        // No service may be touched
+        let mock_service_provider = MockServiceProvider::new();
+
+        for command in [
+            vec!["add", "--repo", "/src", "/dst/.vimrc"],
+            vec!["sync"],
+            vec!["materialize", "/dst"],
+            vec!["restore"],
+        ] {
+            let args = Args::parse_from([APP_NAME, "--dry-run"].into_iter().chain(command));
+            let result = run(args, &mock_service_provider).await;
+            assert!(matches!(result, Err(AppError::DryRunUnsupported)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_unlink_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([APP_NAME, "unlink", "--test", "/src"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+
+        let args = Args::parse_from([APP_NAME, "--dry-run", "unlink", "--test", "/src"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_prune_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([APP_NAME, "prune", "--test", "/src"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+
+        let args = Args::parse_from([APP_NAME, "--dry-run", "prune", "--test", "/src"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_watch_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([APP_NAME, "watch", "--test", "--fold", "/src"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_add_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([
+            APP_NAME,
+            "add",
+            "--test",
+            "--repo",
+            "/src",
+            "/dst/.vimrc",
+            "/dst/.zshrc",
+        ]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+
+        let result = Args::try_parse_from([APP_NAME, "add", "/dst/.vimrc"]);
+        assert!(result.is_err(), "--repo should be required");
+    }
+
+    #[tokio::test]
+    async fn test_run_encrypt_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([
+            APP_NAME,
+            "encrypt",
+            "--test",
+            "--repo",
+            "/src",
+            "/dst/.netrc",
+        ]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+
+        let result = Args::try_parse_from([APP_NAME, "encrypt", "--repo", "/src"]);
+        assert!(result.is_err(), "a file should be required");
+    }
+
+    #[tokio::test]
+    async fn test_run_status_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([APP_NAME, "status", "--test", "--all", "/src"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_diff_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([APP_NAME, "diff", "--test", "--stat", "--repo", "/src"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+
+        let result = Args::try_parse_from([APP_NAME, "diff", "~/.vimrc"]);
+        assert!(result.is_err(), "the dotfiles directory should be required");
+    }
+
     #[tokio::test]
     async fn test_run_materialize_command() {
         let mut mock_service_provider = MockServiceProvider::new();
@@ -215,6 +606,48 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_run_sync_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_load_service()
+            .returning(|| Arc::new(CustomMockLoadService));
+        mock_service_provider
+            .expect_sync_service()
+            .returning(|| Arc::new(CustomMockSyncService));
+
+        let args = Args::parse_from([
+            APP_NAME,
+            "sync",
+            "--test",
+            "--commit",
+            "--repo",
+            "/path/to",
+            "/path/to/config",
+        ]);
+
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_restore_command() {
+        let mut mock_service_provider = MockServiceProvider::new();
+
+        mock_service_provider
+            .expect_link_service()
+            .returning(|| Arc::new(CustomMockLinkService));
+
+        let args = Args::parse_from([APP_NAME, "restore", "20240101-000000"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+
+        let args = Args::parse_from([APP_NAME, "restore", "--list"]);
+        let result = run(args, &mock_service_provider).await;
+        assert!(result.is_ok());
+    }
+
     #[tokio::test]
     async fn test_run_deploy_command() {
         let mut mock_service_provider = MockServiceProvider::new();